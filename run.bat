@@ -1,27 +0,0 @@
-@REM target\release\cg_samegame_rust.exe testcases/test1.json
-@REM target\release\cg_samegame_rust.exe testcases/test2.json
-@REM target\release\cg_samegame_rust.exe testcases/test3.json
-@REM target\release\cg_samegame_rust.exe testcases/test4.json
-@REM target\release\cg_samegame_rust.exe testcases/test5.json
-@REM target\release\cg_samegame_rust.exe testcases/test6.json
-target\release\cg_samegame_rust.exe testcases/test7.json
-target\release\cg_samegame_rust.exe testcases/test8.json
-@REM target\release\cg_samegame_rust.exe testcases/test9.json
-@REM target\release\cg_samegame_rust.exe testcases/test10.json
-@REM target\release\cg_samegame_rust.exe testcases/test12.json
-@REM target\release\cg_samegame_rust.exe testcases/test13.json
-@REM target\release\cg_samegame_rust.exe testcases/test14.json
-@REM target\release\cg_samegame_rust.exe testcases/test16.json
-@REM target\release\cg_samegame_rust.exe testcases/test17.json
-@REM target\release\cg_samegame_rust.exe testcases/test18.json
-@REM target\release\cg_samegame_rust.exe testcases/test19.json
-@REM target\release\cg_samegame_rust.exe testcases/test21.json
-@REM target\release\cg_samegame_rust.exe testcases/test22.json
-@REM target\release\cg_samegame_rust.exe testcases/test23.json
-@REM target\release\cg_samegame_rust.exe testcases/test24.json
-@REM target\release\cg_samegame_rust.exe testcases/test26.json
-@REM target\release\cg_samegame_rust.exe testcases/test27.json
-@REM target\release\cg_samegame_rust.exe testcases/test28.json
-@REM target\release\cg_samegame_rust.exe testcases/test29.json
-
-PAUSE
\ No newline at end of file
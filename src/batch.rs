@@ -0,0 +1,227 @@
+use std::{
+    fs, io,
+    path::{Path, PathBuf},
+    sync::{
+        atomic::{AtomicUsize, Ordering},
+        Mutex,
+    },
+    thread,
+    time::Duration,
+};
+
+/// Outcome of one testcase of a batch.
+pub struct Row {
+    pub title: String,
+    /// Hash of the testcase, shared by a testset and its recolored copy.
+    pub hash: i64,
+    pub actions: String,
+    pub score: i32,
+    /// `Board::upper_bound` of the initial board, no solution scores more.
+    pub bound: i32,
+    pub duration: Duration,
+}
+
+impl Row {
+    /// How far the score is from the bound, in percent of the bound.
+    pub fn gap(&self) -> f64 {
+        gap(self.score as i64, self.bound as i64)
+    }
+}
+
+fn gap(score: i64, bound: i64) -> f64 {
+    if bound <= 0 {
+        return 0.0;
+    }
+    100.0 * (bound - score) as f64 / bound as f64
+}
+
+/// Testcase files designated by a directory (every `.json` inside) or by a
+/// path whose file name may contain `*` and `?` wildcards.
+pub fn find_testcases(pattern: &str) -> io::Result<Vec<PathBuf>> {
+    let path = Path::new(pattern);
+    let (directory, file_pattern) = if path.is_dir() {
+        (path, "*.json")
+    } else {
+        let file_pattern = path
+            .file_name()
+            .and_then(|name| name.to_str())
+            .unwrap_or("");
+        match path.parent() {
+            Some(parent) if !parent.as_os_str().is_empty() => (parent, file_pattern),
+            _ => (Path::new("."), file_pattern),
+        }
+    };
+
+    let mut paths = Vec::new();
+    for entry in fs::read_dir(directory)? {
+        let path = entry?.path();
+        let name = path
+            .file_name()
+            .and_then(|name| name.to_str())
+            .unwrap_or("");
+        if path.is_file() && matches(file_pattern.as_bytes(), name.as_bytes()) {
+            paths.push(path);
+        }
+    }
+
+    // shorter names first, so that test2 comes before test10
+    paths.sort_by_key(|path| (path.as_os_str().len(), path.clone()));
+    Ok(paths)
+}
+
+fn matches(pattern: &[u8], name: &[u8]) -> bool {
+    match (pattern.first(), name.first()) {
+        (None, None) => true,
+        (Some(b'*'), _) => {
+            matches(&pattern[1..], name) || (!name.is_empty() && matches(pattern, &name[1..]))
+        }
+        (Some(b'?'), Some(_)) => matches(&pattern[1..], &name[1..]),
+        (Some(p), Some(n)) if p == n => matches(&pattern[1..], &name[1..]),
+        _ => false,
+    }
+}
+
+/// Runs `solve` on every path with `jobs` threads taking the next testcase
+/// as soon as they are done; the rows come back in the order of `paths`.
+pub fn run<F>(paths: &[PathBuf], jobs: usize, solve: F) -> Vec<Row>
+where
+    F: Fn(&Path) -> Row + Sync,
+{
+    let next = AtomicUsize::new(0);
+    let rows: Mutex<Vec<(usize, Row)>> = Mutex::new(Vec::with_capacity(paths.len()));
+
+    thread::scope(|s| {
+        for _ in 0..jobs.clamp(1, paths.len().max(1)) {
+            s.spawn(|| loop {
+                let i = next.fetch_add(1, Ordering::Relaxed);
+                let Some(path) = paths.get(i) else {
+                    break;
+                };
+                let row = solve(path);
+                rows.lock().unwrap().push((i, row));
+            });
+        }
+    });
+
+    let mut rows = rows.into_inner().unwrap();
+    rows.sort_by_key(|(i, _)| *i);
+    rows.into_iter().map(|(_, row)| row).collect()
+}
+
+/// Rows that count in the CodinGame total, as in `get_result.py`: only the
+/// standard testsets, and the best row of each hash.
+fn counted(rows: &[Row]) -> Vec<&Row> {
+    let mut best: Vec<&Row> = Vec::new();
+    for row in rows.iter().filter(|row| row.title.starts_with("Standard")) {
+        match best.iter_mut().find(|kept| kept.hash == row.hash) {
+            Some(kept) if row.score > kept.score => *kept = row,
+            Some(_) => {}
+            None => best.push(row),
+        }
+    }
+    best
+}
+
+/// CodinGame total: each test has a validator of the same kind, so the sum of
+/// the counted scores is counted twice, as in `get_result.py`.
+pub fn total(rows: &[Row]) -> i64 {
+    2 * counted(rows)
+        .iter()
+        .map(|row| row.score as i64)
+        .sum::<i64>()
+}
+
+/// Bound of the CodinGame total, over the same rows as `total`.
+pub fn total_bound(rows: &[Row]) -> i64 {
+    2 * counted(rows)
+        .iter()
+        .map(|row| row.bound as i64)
+        .sum::<i64>()
+}
+
+pub fn print_summary(rows: &[Row]) {
+    let width = rows
+        .iter()
+        .map(|row| row.title.len())
+        .chain([5])
+        .max()
+        .unwrap();
+
+    println!(
+        "{:<width$} {:>8} {:>8} {:>8} {:>9}",
+        "Title", "Score", "Bound", "Gap (%)", "Time (s)"
+    );
+    for row in rows {
+        println!(
+            "{:<width$} {:>8} {:>8} {:>8.1} {:>9.2}",
+            row.title,
+            row.score,
+            row.bound,
+            row.gap(),
+            row.duration.as_secs_f64()
+        );
+    }
+    let duration: Duration = rows.iter().map(|row| row.duration).sum();
+    println!(
+        "{:<width$} {:>8} {:>8} {:>8.1} {:>9.2}",
+        "Total",
+        total(rows),
+        total_bound(rows),
+        gap(total(rows), total_bound(rows)),
+        duration.as_secs_f64()
+    );
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_matches() {
+        assert!(matches(b"*.json", b"test1.json"));
+        assert!(matches(b"test?.json", b"test1.json"));
+        assert!(!matches(b"test?.json", b"test10.json"));
+        assert!(matches(b"test1*", b"test10.json"));
+        assert!(!matches(b"*.json", b"run.bat"));
+    }
+
+    #[test]
+    fn test_run_order() {
+        let paths: Vec<PathBuf> = (0..10).map(|i| PathBuf::from(i.to_string())).collect();
+
+        let rows = run(&paths, 3, |path| Row {
+            title: path.to_str().unwrap().to_string(),
+            hash: 0,
+            actions: String::new(),
+            score: path.to_str().unwrap().parse().unwrap(),
+            bound: 10,
+            duration: Duration::ZERO,
+        });
+
+        let titles: Vec<&str> = rows.iter().map(|row| row.title.as_str()).collect();
+        assert_eq!(titles, ["0", "1", "2", "3", "4", "5", "6", "7", "8", "9"]);
+        assert_eq!(rows[2].gap(), 80.0);
+    }
+
+    #[test]
+    fn test_total() {
+        let row = |title: &str, hash, score| Row {
+            title: title.to_string(),
+            hash,
+            actions: String::new(),
+            score,
+            bound: 100,
+            duration: Duration::ZERO,
+        };
+        let rows = [
+            row("Standard Testset 1", 1, 30),
+            row("Standard Testset 2", 2, 20),
+            row("Standard Testset 1 (recolored)", 1, 40),
+            row("Standard Testset 2 (recolored)", 2, 10),
+            row("Horizontal lines", 3, 1000),
+        ];
+
+        assert_eq!(total(&rows), 2 * (40 + 20));
+        assert_eq!(total_bound(&rows), 2 * (100 + 100));
+    }
+}
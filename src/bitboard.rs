@@ -0,0 +1,745 @@
+// Alternative backend for `Board`: one 256-bit mask per color, so regions come
+// out of a shift-and-mask flood fill instead of a BFS over the cells. Rows are
+// `W + 1` bits apart and the extra column stays empty, so that horizontal
+// shifts never wrap to the next row; every board with `H * (W + 1) <= 256`
+// fits, which covers the supported grid sizes. Solvers play their rollouts on
+// it through `Game`.
+
+use std::ops::{BitAnd, BitOr, Not};
+
+use crate::{board::Board, game::Game, region::Region, scoring::ScoringRule};
+
+const MASK_BITS: usize = 256;
+
+/// 256 bits, little endian: bit `i` is bit `i % 64` of word `i / 64`.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash)]
+struct Mask([u64; 4]);
+
+impl Mask {
+    const EMPTY: Mask = Mask([0; 4]);
+
+    const fn bit(index: usize) -> Mask {
+        Mask::EMPTY.with(index)
+    }
+
+    const fn with(mut self, index: usize) -> Mask {
+        self.0[index >> 6] |= 1 << (index & 63);
+        self
+    }
+
+    fn is_empty(&self) -> bool {
+        self.0 == [0; 4]
+    }
+
+    fn contains(&self, index: usize) -> bool {
+        self.0[index >> 6] & (1 << (index & 63)) != 0
+    }
+
+    fn count_ones(&self) -> u32 {
+        self.0.iter().map(|w| w.count_ones()).sum()
+    }
+
+    fn lowest(&self) -> Option<usize> {
+        self.0
+            .iter()
+            .enumerate()
+            .find(|(_, &w)| w != 0)
+            .map(|(i, w)| (i << 6) | w.trailing_zeros() as usize)
+    }
+
+    fn ones(&self) -> impl Iterator<Item = usize> + '_ {
+        self.0.iter().enumerate().flat_map(|(i, &word)| {
+            let mut w = word;
+            std::iter::from_fn(move || {
+                (w != 0).then(|| {
+                    let bit = w.trailing_zeros() as usize;
+                    w &= w - 1;
+                    (i << 6) | bit
+                })
+            })
+        })
+    }
+
+    /// Moves every bit `k` positions up (`0 < k < 64`).
+    fn shl(&self, k: u32) -> Mask {
+        let w = self.0;
+        Mask([
+            w[0] << k,
+            (w[1] << k) | (w[0] >> (64 - k)),
+            (w[2] << k) | (w[1] >> (64 - k)),
+            (w[3] << k) | (w[2] >> (64 - k)),
+        ])
+    }
+
+    /// Moves every bit `k` positions down (`0 < k < 64`).
+    fn shr(&self, k: u32) -> Mask {
+        let w = self.0;
+        Mask([
+            (w[0] >> k) | (w[1] << (64 - k)),
+            (w[1] >> k) | (w[2] << (64 - k)),
+            (w[2] >> k) | (w[3] << (64 - k)),
+            w[3] >> k,
+        ])
+    }
+}
+
+impl BitAnd for Mask {
+    type Output = Mask;
+
+    fn bitand(self, rhs: Mask) -> Mask {
+        Mask([
+            self.0[0] & rhs.0[0],
+            self.0[1] & rhs.0[1],
+            self.0[2] & rhs.0[2],
+            self.0[3] & rhs.0[3],
+        ])
+    }
+}
+
+impl BitOr for Mask {
+    type Output = Mask;
+
+    fn bitor(self, rhs: Mask) -> Mask {
+        Mask([
+            self.0[0] | rhs.0[0],
+            self.0[1] | rhs.0[1],
+            self.0[2] | rhs.0[2],
+            self.0[3] | rhs.0[3],
+        ])
+    }
+}
+
+impl Not for Mask {
+    type Output = Mask;
+
+    fn not(self) -> Mask {
+        Mask([!self.0[0], !self.0[1], !self.0[2], !self.0[3]])
+    }
+}
+
+/// Cells of the columns `from..to` of a `W` x `H` board.
+const fn columns<const W: usize, const H: usize>(from: usize, to: usize) -> Mask {
+    let mut mask = Mask::EMPTY;
+    let mut y = 0;
+    while y < H {
+        let mut x = from;
+        while x < to {
+            mask = mask.with(y * (W + 1) + x);
+            x += 1;
+        }
+        y += 1;
+    }
+    mask
+}
+
+/// `masks[x]` holds the cells of the columns `from(x)..to(x)`.
+const fn column_masks<const W: usize, const H: usize>(left_of: bool) -> [Mask; W] {
+    let mut masks = [Mask::EMPTY; W];
+    let mut x = 0;
+    while x < W {
+        masks[x] = if left_of {
+            columns::<W, H>(0, x)
+        } else {
+            columns::<W, H>(x, x + 1)
+        };
+        x += 1;
+    }
+    masks
+}
+
+/// The same game as `Board`, scored by the same `ScoringRule`, with the moves
+/// and the regions designated by the cell indices of `Board`.
+#[derive(Clone, PartialEq, Eq)]
+pub struct BitBoard<const W: usize = 15, const H: usize = 15, const C: usize = 5> {
+    colors: [Mask; C],
+    score: i32,
+    actions: Vec<usize>,
+    rule: ScoringRule,
+}
+
+impl<const W: usize, const H: usize, const C: usize> BitBoard<W, H, C> {
+    const STRIDE: usize = W + 1;
+    const FITS: () = assert!(
+        H * (W + 1) <= MASK_BITS && W + 1 < 64,
+        "the board does not fit in a mask"
+    );
+
+    // every cell of the board, padding column excluded
+    const PLAYABLE: Mask = columns::<W, H>(0, W);
+    const COLUMNS: [Mask; W] = column_masks::<W, H>(false);
+    const LEFT_OF: [Mask; W] = column_masks::<W, H>(true);
+    const BOTTOM_ROW: u64 = (1 << W) - 1;
+
+    fn bit_index(x: usize, y: usize) -> usize {
+        y * Self::STRIDE + x
+    }
+
+    fn to_bit(index: usize) -> usize {
+        let (x, y) = Board::<W, H, C>::to_coordinates(&index);
+        Self::bit_index(x, y)
+    }
+
+    fn to_index(bit: usize) -> usize {
+        Board::<W, H, C>::get_index(bit % Self::STRIDE, bit / Self::STRIDE)
+    }
+
+    fn color_at(&self, bit: usize) -> i8 {
+        self.colors
+            .iter()
+            .position(|mask| mask.contains(bit))
+            .map_or(-1, |color| color as i8)
+    }
+
+    pub fn get_color_counts(&self) -> [u16; C] {
+        self.colors.map(|mask| mask.count_ones() as u16)
+    }
+
+    fn is_empty(&self) -> bool {
+        self.occupied().is_empty()
+    }
+
+    fn occupied(&self) -> Mask {
+        self.colors
+            .iter()
+            .fold(Mask::EMPTY, |acc, &mask| acc | mask)
+    }
+
+    pub fn remaining_cells(&self) -> usize {
+        self.occupied().count_ones() as usize
+    }
+
+    /// Whether no two orthogonal neighbors share a color.
+    pub fn is_terminal(&self) -> bool {
+        let stride = Self::STRIDE as u32;
+        self.colors
+            .iter()
+            .all(|&mask| (mask & mask.shr(1)).is_empty() && (mask & mask.shr(stride)).is_empty())
+    }
+
+    /// Every cell with an empty cell below falls by one row, until none does.
+    fn apply_gravity(&mut self) {
+        let stride = Self::STRIDE as u32;
+        loop {
+            let empty = Self::PLAYABLE & !self.occupied();
+            let falling = self.occupied() & empty.shl(stride);
+            if falling.is_empty() {
+                break;
+            }
+
+            for mask in self.colors.iter_mut() {
+                let moved = *mask & falling;
+                *mask = (*mask & !moved) | moved.shr(stride);
+            }
+        }
+    }
+
+    /// After gravity a column is empty iff its bottom cell is; the columns on
+    /// its right slide one step left, right to left so indices stay valid.
+    fn remove_empty_columns(&mut self) {
+        let bottom_row = self.occupied().0[0] & Self::BOTTOM_ROW;
+
+        for x in (0..W - 1).rev() {
+            if bottom_row & (1 << x) != 0 || bottom_row >> x == 0 {
+                continue;
+            }
+
+            let left = Self::LEFT_OF[x];
+            let right = Self::PLAYABLE & !Self::LEFT_OF[x + 1];
+            for mask in self.colors.iter_mut() {
+                *mask = (*mask & left) | (*mask & right).shr(1);
+            }
+        }
+    }
+
+    fn flood_fill(&self, start_bit: usize, color: usize) -> Mask {
+        let stride = Self::STRIDE as u32;
+        let same_color = self.colors[color];
+        let mut region = Mask::bit(start_bit);
+
+        loop {
+            // the padding column is never set in a color mask, so it stops
+            // the horizontal shifts from wrapping to the next row
+            let grown =
+                (region | region.shl(1) | region.shr(1) | region.shl(stride) | region.shr(stride))
+                    & same_color;
+            if grown == region {
+                return region;
+            }
+            region = grown;
+        }
+    }
+
+    fn to_region(region: Mask, color: i8, first_bit: usize) -> Region {
+        Region {
+            positions: region.ones().map(Self::to_index).collect(),
+            color,
+            first_position: Self::to_index(first_bit),
+        }
+    }
+
+    /// Same regions, in the same order, as `Board::compute_all_regions`.
+    pub fn compute_all_regions(&self) -> Vec<Region> {
+        let mut unvisited = self.occupied();
+        let mut all_regions: Vec<Region> = Vec::new();
+
+        for column in Self::COLUMNS {
+            while let Some(bit) = (unvisited & column).lowest() {
+                let color = self.color_at(bit);
+                let region = self.flood_fill(bit, color as usize);
+                unvisited = unvisited & !region;
+
+                if region.count_ones() < 2 {
+                    continue;
+                }
+                all_regions.push(Self::to_region(region, color, bit));
+            }
+        }
+
+        all_regions
+    }
+
+    pub fn play_region(&mut self, region: &Region) {
+        self.actions.push(region.first_position);
+
+        let removed = region
+            .positions
+            .iter()
+            .fold(Mask::EMPTY, |acc, &i| acc | Mask::bit(Self::to_bit(i)));
+        let color = region.color as usize;
+        self.colors[color] = self.colors[color] & !removed;
+        self.score += self.rule.region_points(region.len());
+
+        self.apply_gravity();
+        self.remove_empty_columns();
+
+        if self.is_empty() {
+            self.score += self.rule.clear_bonus();
+        } else if self.rule.scores_leftovers() && self.is_terminal() {
+            self.score += self.rule.end_of_game(&self.get_color_counts());
+        }
+    }
+
+    /// Key of the cells: not kept up to date by the moves like the zobrist
+    /// key of `Board`, but a hash of the masks.
+    pub fn zobrist(&self) -> u64 {
+        self.colors
+            .iter()
+            .flat_map(|mask| mask.0)
+            .fold(0, |hash: u64, word| {
+                (hash.rotate_left(5) ^ word).wrapping_mul(0x9E37_79B9_7F4A_7C15)
+            })
+    }
+}
+
+impl<const W: usize, const H: usize, const C: usize> Game<W, H, C> for BitBoard<W, H, C> {
+    fn from_board(board: &Board<W, H, C>) -> BitBoard<W, H, C> {
+        let () = Self::FITS;
+
+        let mut colors = [Mask::EMPTY; C];
+        for y in 0..H {
+            for x in 0..W {
+                let cell = board.get(x, y);
+                if cell >= 0 {
+                    colors[cell as usize] = colors[cell as usize].with(Self::bit_index(x, y));
+                }
+            }
+        }
+
+        BitBoard {
+            colors,
+            score: board.get_score(),
+            actions: board.get_actions().to_vec(),
+            rule: board.rule(),
+        }
+    }
+
+    fn into_board(self, start: &Board<W, H, C>) -> Board<W, H, C> {
+        let mut board = start.clone();
+        for &index in &self.actions[start.get_actions().len()..] {
+            board.play_index(index);
+        }
+        board
+    }
+
+    fn get_score(&self) -> i32 {
+        self.score
+    }
+
+    fn zobrist(&self) -> u64 {
+        BitBoard::zobrist(self)
+    }
+
+    fn remaining_cells(&self) -> usize {
+        BitBoard::remaining_cells(self)
+    }
+
+    fn compute_all_regions(&self) -> Vec<Region> {
+        BitBoard::compute_all_regions(self)
+    }
+
+    fn play_region(&mut self, region: &Region) {
+        BitBoard::play_region(self, region)
+    }
+}
+
+/// The rest of the `Board` API, only needed to run its tests on this backend.
+#[cfg(test)]
+impl<const W: usize, const H: usize, const C: usize> BitBoard<W, H, C> {
+    pub fn new(initial_board: [[i8; W]; H]) -> BitBoard<W, H, C> {
+        BitBoard::with_rule(initial_board, ScoringRule::default())
+    }
+
+    pub fn with_rule(initial_board: [[i8; W]; H], rule: ScoringRule) -> BitBoard<W, H, C> {
+        BitBoard::from_board(&Board::with_rule(initial_board, rule))
+    }
+
+    pub fn get_actions(&self) -> &[usize] {
+        &self.actions
+    }
+
+    pub fn get(&self, x: usize, y: usize) -> i8 {
+        self.color_at(Self::bit_index(x, y))
+    }
+
+    /// Region of the cell at `start_index`, empty for an empty cell.
+    pub fn compute_region_index(&self, start_index: usize) -> Region {
+        let start_bit = Self::to_bit(start_index);
+        let color = self.color_at(start_bit);
+        if color < 0 {
+            return Region {
+                positions: vec![],
+                color,
+                first_position: start_index,
+            };
+        }
+
+        Self::to_region(self.flood_fill(start_bit, color as usize), color, start_bit)
+    }
+
+    pub fn play(&mut self, x: usize, y: usize) {
+        let region = self.compute_region_index(Board::<W, H, C>::get_index(x, y));
+        if region.len() >= 2 {
+            self.play_region(&region);
+        }
+    }
+
+    pub fn upper_bound(&self) -> i32 {
+        if self.is_terminal() {
+            return 0;
+        }
+        self.rule.upper_bound(&self.get_color_counts())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use rand::{rngs::StdRng, Rng, SeedableRng};
+
+    use super::*;
+    use crate::test_util::level;
+
+    // the tests of `board.rs` that do not rely on `Board` only features
+    // (`try_play`, `unplay`, `final_score`)
+
+    #[test]
+    fn test_board_1() {
+        let mut board: BitBoard = BitBoard::new(level(1));
+        assert_eq!(board.get_score(), 0);
+        assert_eq!(board.get_color_counts(), [45, 90, 60, 15, 15]);
+
+        for _ in 0..12 {
+            board.play(0, 0);
+        }
+
+        assert_eq!(board.get_score(), 4873);
+        assert_eq!(board.get_color_counts(), [0, 0, 0, 0, 0]);
+    }
+
+    #[test]
+    fn test_board_2() {
+        let mut board: BitBoard = BitBoard::new(level(1));
+        assert_eq!(board.get_score(), 0);
+        assert_eq!(board.get_color_counts(), [45, 90, 60, 15, 15]);
+
+        for (x, y) in [
+            (0, 1),
+            (0, 4),
+            (0, 3),
+            (0, 5),
+            (0, 5),
+            (0, 5),
+            (0, 0),
+            (0, 0),
+        ] {
+            board.play(x, y);
+        }
+
+        assert_eq!(board.get_score(), 11607);
+        assert_eq!(board.get_color_counts(), [0, 0, 0, 0, 0]);
+    }
+
+    #[test]
+    fn test_board_3() {
+        let mut board: BitBoard = BitBoard::new(level(2));
+        assert_eq!(board.get_color_counts(), [45, 75, 45, 30, 30]);
+
+        for (x, y) in [
+            (5, 0),
+            (1, 0),
+            (1, 0),
+            (2, 0),
+            (2, 0),
+            (0, 0),
+            (0, 0),
+            (0, 0),
+        ] {
+            board.play(x, y);
+        }
+
+        assert_eq!(board.get_score(), 7107);
+        assert_eq!(board.get_color_counts(), [0, 0, 0, 0, 0]);
+    }
+
+    #[test]
+    fn test_board_4() {
+        let mut board: BitBoard = BitBoard::new(level(3));
+        assert_eq!(board.get_color_counts(), [63, 45, 45, 36, 36]);
+
+        for (x, y) in [
+            (0, 14),
+            (6, 11),
+            (6, 11),
+            (3, 5),
+            (0, 8),
+            (6, 5),
+            (6, 8),
+            (6, 5),
+            (0, 5),
+            (0, 5),
+            (6, 5),
+            (6, 5),
+            (0, 2),
+            (0, 2),
+        ] {
+            board.play(x, y);
+        }
+
+        assert_eq!(board.get_score(), 5421);
+        assert_eq!(board.get_color_counts(), [0, 0, 0, 0, 0]);
+    }
+
+    #[test]
+    fn test_all_regions() {
+        let board: BitBoard = BitBoard::new(level(3));
+
+        assert_eq!(board.compute_all_regions().len(), 23);
+    }
+
+    #[test]
+    fn test_legal_moves() {
+        let board: BitBoard = BitBoard::new(level(2));
+        let regions = board.compute_all_regions();
+
+        assert!(regions.iter().all(|region| region.len() >= 2));
+        assert_eq!(regions.len(), 10);
+        assert_eq!(regions[0].first_position, <Board>::get_index(0, 0));
+    }
+
+    #[test]
+    fn test_play() {
+        let mut board: BitBoard = BitBoard::new(level(3));
+
+        board.play(6, 11);
+
+        assert_eq!(board.get(6, 0), 0);
+        assert_eq!(board.get(6, 3), 3);
+        assert_eq!(board.get(6, 6), 4);
+        assert_eq!(board.get(6, 9), 3);
+        assert_eq!(board.get(6, 12), -1);
+
+        // an empty cell has an empty region, and playing it does nothing
+        let region = board.compute_region_index(<Board>::get_index(6, 12));
+        assert!(region.positions.is_empty());
+        let before = board.clone();
+        board.play(6, 12);
+        assert!(board == before);
+    }
+
+    #[test]
+    fn test_zobrist() {
+        let mut board: BitBoard = BitBoard::new(level(3));
+
+        for (x, y) in [(0, 14), (6, 11), (6, 11), (3, 5), (0, 8), (6, 5)] {
+            board.play(x, y);
+
+            let mut cells = [[-1; 15]; 15];
+            for (y, row) in cells.iter_mut().enumerate() {
+                for (x, cell) in row.iter_mut().enumerate() {
+                    *cell = board.get(x, y);
+                }
+            }
+            assert_eq!(board.zobrist(), <BitBoard>::new(cells).zobrist());
+        }
+
+        assert_ne!(board.zobrist(), <BitBoard>::new(level(3)).zobrist());
+    }
+
+    #[test]
+    fn test_other_sizes() {
+        // vertical stripes of 2 columns, colors 0 to 2
+        let mut cells = [[0i8; 20]; 10];
+        for row in cells.iter_mut() {
+            for (x, cell) in row.iter_mut().enumerate() {
+                *cell = ((x / 2) % 3) as i8;
+            }
+        }
+        let mut board: BitBoard<20, 10, 3> = BitBoard::new(cells);
+
+        assert_eq!(board.get_color_counts(), [80, 60, 60]);
+        assert_eq!(board.compute_all_regions().len(), 10);
+
+        board.play(19, 9);
+        assert_eq!(board.get_score(), 18 * 18);
+        assert_eq!(board.get(18, 0), -1);
+        assert_eq!(board.get(17, 0), 2);
+
+        while let Some(region) = board.compute_all_regions().pop() {
+            board.play_region(&region);
+        }
+        assert_eq!(board.get_color_counts(), [0, 0, 0]);
+
+        let mut small: BitBoard<3, 2, 2> = BitBoard::new([[0, 1, 1], [0, 0, 1]]);
+        assert_eq!(small.compute_all_regions().len(), 2);
+        small.play(0, 0);
+        assert_eq!(small.get(0, 0), 1);
+        assert_eq!(small.get(2, 0), -1);
+    }
+
+    #[test]
+    fn test_scoring_rules() {
+        let left: [[i8; 4]; 1] = [[0, 0, 1, 2]];
+        let clear: [[i8; 4]; 1] = [[0, 0, 1, 1]];
+
+        for (rule, left_score, clear_score) in [
+            (ScoringRule::CodinGame, 0, 1000),
+            (ScoringRule::JsGames, -2, 1000),
+            (ScoringRule::Clickomania, 2, 4),
+        ] {
+            let mut board: BitBoard<4, 1, 3> = BitBoard::with_rule(left, rule);
+            board.play(0, 0);
+            assert_eq!(board.get_score(), left_score, "{}", rule);
+
+            let mut board: BitBoard<4, 1, 3> = BitBoard::with_rule(clear, rule);
+            board.play(0, 0);
+            board.play(0, 0);
+            assert_eq!(board.get_score(), clear_score, "{}", rule);
+        }
+    }
+
+    #[test]
+    fn test_terminal() {
+        let mut board: BitBoard = BitBoard::new(level(3));
+        assert!(!board.is_terminal());
+        assert_eq!(board.remaining_cells(), 225);
+
+        while let Some(region) = board.compute_all_regions().pop() {
+            assert!(!board.is_terminal());
+            board.play_region(&region);
+        }
+        assert!(board.is_terminal());
+        let cells = (0..15)
+            .flat_map(|y| (0..15).map(move |x| (x, y)))
+            .filter(|&(x, y)| board.get(x, y) >= 0)
+            .count();
+        assert_eq!(board.remaining_cells(), cells);
+    }
+
+    #[test]
+    fn test_upper_bound() {
+        for rule in [
+            ScoringRule::CodinGame,
+            ScoringRule::JsGames,
+            ScoringRule::Clickomania,
+        ] {
+            for level_index in [1, 2, 3] {
+                let mut board: BitBoard = BitBoard::with_rule(level(level_index), rule);
+
+                // every position of a game is bounded by the final score
+                let mut bounds = vec![];
+                while let Some(region) = board.compute_all_regions().pop() {
+                    bounds.push(board.get_score() + board.upper_bound());
+                    board.play_region(&region);
+                }
+                assert_eq!(board.upper_bound(), 0);
+                assert!(bounds.iter().all(|&bound| bound >= board.get_score()));
+            }
+        }
+
+        let board: BitBoard<4, 1, 3> = BitBoard::new([[0, 0, 1, 1]]);
+        assert_eq!(board.upper_bound(), 1000);
+    }
+
+    fn same_games_as_board<const W: usize, const H: usize, const C: usize>(
+        rng: &mut StdRng,
+        rule: ScoringRule,
+    ) {
+        let mut cells = [[0i8; W]; H];
+        for row in cells.iter_mut() {
+            for cell in row.iter_mut() {
+                *cell = rng.gen_range(0..C as i8);
+            }
+        }
+        let start: Board<W, H, C> = Board::with_rule(cells, rule);
+        let mut board = start.clone();
+        let mut bitboard = BitBoard::from_board(&board);
+
+        loop {
+            let regions = board.compute_all_regions();
+            let bit_regions = bitboard.compute_all_regions();
+            assert_eq!(regions.len(), bit_regions.len());
+            for (a, b) in regions.iter().zip(bit_regions.iter()) {
+                assert_eq!(a.first_position, b.first_position);
+                assert_eq!(a.color, b.color);
+                assert_eq!(a.len(), b.len());
+            }
+            assert_eq!(board.is_terminal(), bitboard.is_terminal());
+            if regions.is_empty() {
+                break;
+            }
+
+            let picked = rng.gen_range(0..regions.len());
+            board.play_region(&regions[picked]);
+            bitboard.play_region(&bit_regions[picked]);
+
+            assert_eq!(board.get_score(), bitboard.get_score());
+            assert_eq!(board.get_color_counts(), bitboard.get_color_counts());
+            for x in 0..W {
+                for y in 0..H {
+                    assert_eq!(board.get(x, y), bitboard.get(x, y));
+                }
+            }
+        }
+
+        assert_eq!(board.get_actions(), bitboard.get_actions());
+        let replayed = bitboard.into_board(&start);
+        assert!(replayed == board);
+        assert_eq!(replayed.get_score(), board.get_score());
+    }
+
+    #[test]
+    fn test_same_games_as_board() {
+        let mut rng = StdRng::seed_from_u64(42);
+
+        for rule in [
+            ScoringRule::CodinGame,
+            ScoringRule::JsGames,
+            ScoringRule::Clickomania,
+        ] {
+            for _ in 0..10 {
+                same_games_as_board::<15, 15, 2>(&mut rng, rule);
+                same_games_as_board::<15, 15, 5>(&mut rng, rule);
+                same_games_as_board::<20, 10, 6>(&mut rng, rule);
+            }
+        }
+    }
+}
@@ -5,53 +5,152 @@ use std::{
     hash::{Hash, Hasher},
 };
 
-use crate::region::Region;
+use crate::{
+    region::Region,
+    scoring::{ScoreBreakdown, ScoringRule},
+};
+
+// one random key per (cell, color), xored together for the occupied cells;
+// the keys are drawn in cell index order, padding included, so that they
+// do not depend on the width
+const fn generate_zobrist_keys<const W: usize, const H: usize, const C: usize>(
+    shift: u32,
+) -> [[[u64; C]; W]; H] {
+    let mut keys = [[[0; C]; W]; H];
+    let mut state: u64 = 0x9E37_79B9_7F4A_7C15;
+    let mut i = 0;
+    while i < (H << shift) * C {
+        // splitmix64
+        state = state.wrapping_add(0x9E37_79B9_7F4A_7C15);
+        let mut z = state;
+        z = (z ^ (z >> 30)).wrapping_mul(0xBF58_476D_1CE4_E5B9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94D0_49BB_1331_11EB);
+
+        let index = i / C;
+        let (x, y) = (index & ((1 << shift) - 1), index >> shift);
+        if x < W {
+            keys[y][x][i % C] = z ^ (z >> 31);
+        }
+        i += 1;
+    }
+    keys
+}
 
-const BOARD_SIZE: usize = 16; // Using 16 for bitwise operations
-const GAME_SIZE: usize = 15; // Actual game size
-const TOTAL_CELLS: usize = BOARD_SIZE * BOARD_SIZE;
-const ROW_MASK: usize = BOARD_SIZE - 1; // 0b1111 for bitwise AND
+/// What a move changed, in the order it happened, so that `Board::unplay`
+/// can restore the exact previous state without cloning the board.
+pub struct Undo {
+    color: i8,
+    removed: Vec<u16>,
+    // cells moved by gravity, (from, to)
+    moved: Vec<(u16, u16)>,
+    // columns moved left when an empty column is removed, (from_x, to_x)
+    shifted_columns: Vec<(u8, u8)>,
+    score_delta: i32,
+}
+
+/// Why `try_play` refused a move.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MoveError {
+    OutOfBounds,
+    EmptyCell,
+    SingletonRegion,
+    GameOver,
+    /// The region is not the current group of its first cell: its cells
+    /// changed color or it covers only part of the group.
+    StaleRegion,
+}
+
+impl fmt::Display for MoveError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> Result<(), fmt::Error> {
+        let message = match self {
+            MoveError::OutOfBounds => "out of the board",
+            MoveError::EmptyCell => "empty cell",
+            MoveError::SingletonRegion => "no neighbor of the same color",
+            MoveError::GameOver => "no move left",
+            MoveError::StaleRegion => "the region does not match the board",
+        };
+        f.write_str(message)
+    }
+}
 
-pub struct Board {
-    board: [i8; TOTAL_CELLS],
-    score: u32,
-    color_counts: [u8; 5],
+/// A `W` x `H` game with `C` colors scored by a `ScoringRule`; the default is
+/// the CodinGame board.
+///
+/// Cells are designated by an index `(y << SHIFT) | x` where rows are padded
+/// to a power of two, so that a 15 wide board has the usual `y * 16 + x`.
+pub struct Board<const W: usize = 15, const H: usize = 15, const C: usize = 5> {
+    board: [[i8; W]; H],
+    score: i32,
+    color_counts: [u16; C],
     actions: Vec<usize>,
+    zobrist: u64,
+    rule: ScoringRule,
 }
 
-impl Board {
-    pub fn new(initial_board: [[i8; GAME_SIZE]; GAME_SIZE]) -> Board {
-        let mut board = [-1; TOTAL_CELLS]; // Initialize all cells as empty
-        let mut color_counts = [0; 5];
+impl<const W: usize, const H: usize, const C: usize> Board<W, H, C> {
+    /// Bits of the x coordinate in a cell index.
+    const SHIFT: u32 = usize::BITS - (W - 1).leading_zeros();
+    const ROW_MASK: usize = (1 << Self::SHIFT) - 1;
+    const ROW_STRIDE: usize = 1 << Self::SHIFT;
+
+    const ZOBRIST_KEYS: &'static [[[u64; C]; W]; H] =
+        &generate_zobrist_keys::<W, H, C>(Self::SHIFT);
+
+    pub fn new(initial_board: [[i8; W]; H]) -> Board<W, H, C> {
+        Board::with_rule(initial_board, ScoringRule::default())
+    }
+
+    pub fn with_rule(initial_board: [[i8; W]; H], rule: ScoringRule) -> Board<W, H, C> {
+        let mut color_counts = [0; C];
+        let mut zobrist = 0;
 
         for (y, row) in initial_board.iter().enumerate() {
             for (x, &cell) in row.iter().enumerate() {
+                // anything negative is an empty cell
                 if cell >= 0 {
-                    let index = Board::get_index(x, y);
-                    board[index] = cell;
                     color_counts[cell as usize] += 1;
+                    zobrist ^= Self::ZOBRIST_KEYS[y][x][cell as usize];
                 }
             }
         }
 
         Board {
-            board,
+            board: initial_board.map(|row| row.map(|cell| cell.max(-1))),
             score: 0,
             color_counts,
             actions: Vec::new(),
+            zobrist,
+            rule,
         }
     }
 
-    pub fn get_score(&self) -> u32 {
+    pub fn get_score(&self) -> i32 {
         self.score
     }
 
+    pub fn rule(&self) -> ScoringRule {
+        self.rule
+    }
+
+    pub fn get_color_counts(&self) -> [u16; C] {
+        self.color_counts
+    }
+
+    /// Zobrist key of the cells, kept up to date by every move.
+    pub fn zobrist(&self) -> u64 {
+        self.zobrist
+    }
+
+    pub fn get_actions(&self) -> &[usize] {
+        &self.actions
+    }
+
     pub fn get_actions_str(&self) -> String {
         let strings: Vec<String> = self
             .actions
             .iter()
             .map(|idx| {
-                let (x, y) = Board::to_coordinates(idx);
+                let (x, y) = Self::to_coordinates(idx);
                 format!("{} {}", x, y)
             })
             .collect();
@@ -59,12 +158,9 @@ impl Board {
         itertools::join(strings, ";")
     }
 
-    pub fn get_color_of_index(&self, idx: &usize) -> &i8 {
-        &self.board[*idx]
-    }
-
+    /// Unchecked fast path: an empty cell or a single cell is silently ignored.
     pub fn play_index(&mut self, index: usize) {
-        let picked_color = self.board[index];
+        let picked_color = self.cell(index);
         if picked_color < 0 {
             return;
         }
@@ -78,41 +174,201 @@ impl Board {
     }
 
     pub fn play(&mut self, x: usize, y: usize) {
-        let index = Board::get_index(x, y);
+        let index = Self::get_index(x, y);
         self.play_index(index)
     }
 
     pub fn play_region(&mut self, region: &Region) {
+        self.inner_play_region(region, None);
+    }
+
+    /// Checked version of `play`, for moves that come from outside the search.
+    pub fn try_play(&mut self, x: usize, y: usize) -> Result<(), MoveError> {
+        if x >= W || y >= H {
+            return Err(MoveError::OutOfBounds);
+        }
+        let region = self.compute_region(x, y);
+        self.try_play_region(&region)
+    }
+
+    /// Checked version of `play_region`: the region must be exactly the group
+    /// of its first cell on the current board.
+    pub fn try_play_region(&mut self, region: &Region) -> Result<(), MoveError> {
+        let (x, y) = Self::to_coordinates(&region.first_position);
+        if x >= W || y >= H {
+            return Err(MoveError::OutOfBounds);
+        }
+        if self.is_terminal() {
+            return Err(MoveError::GameOver);
+        }
+        if self.cell(region.first_position) < 0 {
+            return Err(MoveError::EmptyCell);
+        }
+        let current = self.compute_region_index(region.first_position);
+        if current.color != region.color || !current.same_cells(region) {
+            return Err(MoveError::StaleRegion);
+        }
+        if region.len() < 2 {
+            return Err(MoveError::SingletonRegion);
+        }
+
+        self.play_region(region);
+        Ok(())
+    }
+
+    /// Same as `play_region`, recording what is needed to `unplay` the move.
+    pub fn play_region_with_undo(&mut self, region: &Region) -> Undo {
+        let mut undo = Undo {
+            color: region.color,
+            removed: Vec::with_capacity(region.len()),
+            moved: Vec::new(),
+            shifted_columns: Vec::new(),
+            score_delta: 0,
+        };
+        self.inner_play_region(region, Some(&mut undo));
+        undo
+    }
+
+    /// Reverts the last move played with `play_region_with_undo`.
+    pub fn unplay(&mut self, undo: Undo) {
+        for &(from_x, to_x) in undo.shifted_columns.iter().rev() {
+            for y in 0..H {
+                let from = Self::get_index(from_x as usize, y);
+                let to = Self::get_index(to_x as usize, y);
+                if self.cell(to) >= 0 {
+                    self.move_cell(to, from);
+                }
+            }
+        }
+
+        for &(from, to) in undo.moved.iter().rev() {
+            self.move_cell(to as usize, from as usize);
+        }
+
+        for &i in undo.removed.iter() {
+            self.set_cell(i as usize, undo.color);
+            self.zobrist ^= Self::zobrist_key(i as usize, undo.color);
+        }
+
+        self.score -= undo.score_delta;
+        self.color_counts[undo.color as usize] += undo.removed.len() as u16;
+        self.actions.pop();
+    }
+
+    fn inner_play_region(&mut self, region: &Region, mut undo: Option<&mut Undo>) {
+        let score_before = self.score;
         self.actions.push(region.first_position);
 
-        for i in region.positions.iter() {
-            self.board[*i] = -1;
+        for &i in region.positions.iter() {
+            self.set_cell(i, -1);
+            self.zobrist ^= Self::zobrist_key(i, region.color);
         }
 
-        self.score += region.score();
-        self.color_counts[region.color as usize] -= region.len() as u8;
+        self.score += self.rule.region_points(region.len());
+        self.color_counts[region.color as usize] -= region.len() as u16;
 
-        let (start_x, start_y, end_x) = self.get_region_boundaries(&region);
-        self.apply_gravity(start_x, start_y, end_x);
-        self.remove_empty_columns(start_x);
+        let (start_x, start_y, end_x) = self.get_region_boundaries(region);
+        self.apply_gravity(start_x, start_y, end_x, undo.as_deref_mut());
+        self.remove_empty_columns(start_x, undo.as_deref_mut());
 
-        // if the board is fully empty, add 1000 points
         if self.is_empty() {
-            self.score += 1000;
+            self.score += self.rule.clear_bonus();
+        } else if self.rule.scores_leftovers() && self.is_terminal() {
+            self.score += self.rule.end_of_game(&self.color_counts);
+        }
+
+        if let Some(undo) = undo {
+            undo.removed
+                .extend(region.positions.iter().map(|&i| i as u16));
+            undo.score_delta = self.score - score_before;
         }
     }
 
+    fn cell(&self, index: usize) -> i8 {
+        self.board[index >> Self::SHIFT][index & Self::ROW_MASK]
+    }
+
+    fn set_cell(&mut self, index: usize, color: i8) {
+        self.board[index >> Self::SHIFT][index & Self::ROW_MASK] = color;
+    }
+
+    fn zobrist_key(index: usize, color: i8) -> u64 {
+        Self::ZOBRIST_KEYS[index >> Self::SHIFT][index & Self::ROW_MASK][color as usize]
+    }
+
+    fn move_cell(&mut self, from: usize, to: usize) {
+        let color = self.cell(from);
+        self.set_cell(to, color);
+        self.set_cell(from, -1);
+        self.zobrist ^= Self::zobrist_key(from, color) ^ Self::zobrist_key(to, color);
+    }
+
     fn is_empty(&self) -> bool {
-        self.board[Board::get_index(0, 0)] == -1
+        self.board[0][0] == -1
+    }
+
+    /// Whether no move is left, that is no two orthogonal neighbors share a
+    /// color; cheaper than looking for a region with `legal_moves`.
+    pub fn is_terminal(&self) -> bool {
+        for x in 0..W {
+            for y in 0..H {
+                let color = self.board[y][x];
+                if color < 0 {
+                    // columns are packed to the left, an empty one ends the board
+                    if y == 0 {
+                        return true;
+                    }
+                    break;
+                }
+                if (y + 1 < H && self.board[y + 1][x] == color)
+                    || (x + 1 < W && self.board[y][x + 1] == color)
+                {
+                    return false;
+                }
+            }
+        }
+        true
+    }
+
+    /// Most points the rest of the game can add, see `ScoringRule::upper_bound`;
+    /// `get_score() + upper_bound()` is never below the final score.
+    pub fn upper_bound(&self) -> i32 {
+        if self.is_terminal() {
+            return 0;
+        }
+        self.rule.upper_bound(&self.color_counts)
+    }
+
+    pub fn remaining_cells(&self) -> usize {
+        self.color_counts.iter().map(|&count| count as usize).sum()
+    }
+
+    /// Where the points of a finished game come from, `None` while moves are
+    /// left.
+    pub fn final_score(&self) -> Option<ScoreBreakdown> {
+        if !self.is_terminal() {
+            return None;
+        }
+
+        let (clear_bonus, leftover_penalty) = if self.is_empty() {
+            (self.rule.clear_bonus(), 0)
+        } else {
+            (0, self.rule.end_of_game(&self.color_counts))
+        };
+        Some(ScoreBreakdown {
+            region_points: self.score - clear_bonus - leftover_penalty,
+            clear_bonus,
+            leftover_penalty,
+        })
     }
 
     fn get_region_boundaries(&self, region_removed: &Region) -> (usize, usize, usize) {
-        let mut start_x = GAME_SIZE;
+        let mut start_x = W;
         let mut end_x = 0;
-        let mut start_y = GAME_SIZE;
+        let mut start_y = H;
 
         for &index in region_removed.positions.iter() {
-            let (x, y) = Board::to_coordinates(&index);
+            let (x, y) = Self::to_coordinates(&index);
             if y < start_y {
                 start_y = y;
             }
@@ -127,34 +383,47 @@ impl Board {
         (start_x, start_y, end_x)
     }
 
-    fn apply_gravity(&mut self, start_x: usize, start_y: usize, end_x: usize) {
+    fn apply_gravity(
+        &mut self,
+        start_x: usize,
+        start_y: usize,
+        end_x: usize,
+        mut undo: Option<&mut Undo>,
+    ) {
         for x in start_x..=end_x {
-            let mut cursor = Board::get_index(x, start_y);
-            for y in start_y..15 {
-                if self.board[cursor] >= 0 {
-                    cursor += BOARD_SIZE;
+            let mut cursor = Self::get_index(x, start_y);
+            for y in start_y..H {
+                if self.cell(cursor) >= 0 {
+                    cursor += Self::ROW_STRIDE;
                     continue;
                 }
-                let idx = Board::get_index(x, y);
-                if self.board[idx] >= 0 {
-                    self.board[cursor] = self.board[idx];
-                    self.board[idx] = -1;
-                    cursor += BOARD_SIZE;
+                let idx = Self::get_index(x, y);
+                if self.cell(idx) >= 0 {
+                    self.move_cell(idx, cursor);
+                    if let Some(undo) = undo.as_deref_mut() {
+                        undo.moved.push((idx as u16, cursor as u16));
+                    }
+                    cursor += Self::ROW_STRIDE;
                 }
             }
         }
     }
 
-    fn remove_empty_columns(&mut self, start_x: usize) {
+    fn remove_empty_columns(&mut self, start_x: usize, mut undo: Option<&mut Undo>) {
         let mut cursor_1_x = start_x;
-        for cursor_2_x in start_x..GAME_SIZE {
+        for cursor_2_x in start_x..W {
             if self.get(cursor_2_x, 0) >= 0 {
                 if cursor_2_x > cursor_1_x {
-                    for y in 0..15 {
-                        let idx_cur1 = Board::get_index(cursor_1_x, y);
-                        let idx_cur2 = Board::get_index(cursor_2_x, y);
-                        self.board[idx_cur1] = self.board[idx_cur2];
-                        self.board[idx_cur2] = -1;
+                    for y in 0..H {
+                        let idx_cur1 = Self::get_index(cursor_1_x, y);
+                        let idx_cur2 = Self::get_index(cursor_2_x, y);
+                        if self.cell(idx_cur2) >= 0 {
+                            self.move_cell(idx_cur2, idx_cur1);
+                        }
+                    }
+                    if let Some(undo) = undo.as_deref_mut() {
+                        undo.shifted_columns
+                            .push((cursor_2_x as u8, cursor_1_x as u8));
                     }
                 }
                 cursor_1_x += 1;
@@ -163,39 +432,36 @@ impl Board {
     }
 
     pub fn compute_region_index(&self, start_index: usize) -> Region {
-        let mut visited = [false; TOTAL_CELLS];
+        let mut visited = [[false; W]; H];
         self.inner_compute_region(start_index, &mut visited)
     }
 
-    fn inner_compute_region(
-        &self,
-        start_index: usize,
-        visited: &mut [bool; TOTAL_CELLS],
-    ) -> Region {
+    fn inner_compute_region(&self, start_index: usize, visited: &mut [[bool; W]; H]) -> Region {
         let mut region = Vec::new();
         let mut stack = VecDeque::new();
-        let color = self.board[start_index];
+        let color = self.cell(start_index);
 
         stack.push_back(start_index);
 
         while let Some(index) = stack.pop_front() {
-            if visited[index] {
+            let (x, y) = Self::to_coordinates(&index);
+            if visited[y][x] {
                 continue;
             }
-            visited[index] = true;
+            visited[y][x] = true;
             region.push(index);
 
-            let (x, y) = Board::to_coordinates(&index);
-
             for (dx, dy) in [(1, 0), (-1, 0), (0, 1), (0, -1)] {
                 let nx = x as i32 + dx;
                 let ny = y as i32 + dy;
 
-                if nx >= 0 && nx < GAME_SIZE as i32 && ny >= 0 && ny < GAME_SIZE as i32 {
-                    let neighbor = Board::get_index(nx as usize, ny as usize);
-                    if self.board[neighbor] == color {
-                        stack.push_back(neighbor);
-                    }
+                if nx >= 0
+                    && nx < W as i32
+                    && ny >= 0
+                    && ny < H as i32
+                    && self.board[ny as usize][nx as usize] == color
+                {
+                    stack.push_back(Self::get_index(nx as usize, ny as usize));
                 }
             }
         }
@@ -208,60 +474,57 @@ impl Board {
     }
 
     pub fn compute_all_regions(&self) -> Vec<Region> {
-        let mut visited = [false; TOTAL_CELLS];
-        let mut all_regions: Vec<Region> = Vec::new();
-
-        for x in 0..15 {
-            for y in 0..15 {
-                let index = Board::get_index(x, y);
-                if visited[index] {
-                    continue;
-                }
+        self.legal_moves().collect()
+    }
 
-                let color = self.board[index];
-                if color < 0 {
-                    break;
-                }
+    /// Regions of at least two cells, column by column from the bottom left.
+    pub fn legal_moves(&self) -> impl Iterator<Item = Region> + '_ {
+        let mut visited = [[false; W]; H];
 
-                let region = self.inner_compute_region(index, &mut visited);
-                if region.len() < 2 {
-                    continue;
+        (0..W)
+            .flat_map(move |x| {
+                (0..H)
+                    .take_while(move |&y| self.board[y][x] >= 0)
+                    .map(move |y| (x, y))
+            })
+            .filter_map(move |(x, y)| {
+                if visited[y][x] {
+                    return None;
                 }
-                all_regions.push(region);
-            }
-        }
-        all_regions
+                let region = self.inner_compute_region(Self::get_index(x, y), &mut visited);
+                (region.len() >= 2).then_some(region)
+            })
     }
 
-    fn get_index(x: usize, y: usize) -> usize {
-        (y << 4) | x // row * 16 + col
+    pub fn get_index(x: usize, y: usize) -> usize {
+        (y << Self::SHIFT) | x // row * 16 + col on the default board
     }
 
     pub fn to_coordinates(index: &usize) -> (usize, usize) {
-        let y = index >> 4;
-        let x = index & ROW_MASK;
+        let y = index >> Self::SHIFT;
+        let x = index & Self::ROW_MASK;
         (x, y)
     }
 
     pub fn get(&self, x: usize, y: usize) -> i8 {
-        self.board[Board::get_index(x, y)]
+        self.board[y][x]
     }
 
     pub fn compute_region(&self, x: usize, y: usize) -> Region {
-        let start_index = Board::get_index(x, y);
+        let start_index = Self::get_index(x, y);
         self.compute_region_index(start_index)
     }
 }
 
-impl Debug for Board {
+impl<const W: usize, const H: usize, const C: usize> Debug for Board<W, H, C> {
     fn fmt(&self, f: &mut Formatter<'_>) -> Result<(), fmt::Error> {
         writeln!(f, "Score: {} - {:?}", self.score, self.color_counts)?;
-        for y in 0..15 {
-            for x in 0..15 {
-                let char = if self.get(x, 14 - y) < 0 {
+        for y in (0..H).rev() {
+            for x in 0..W {
+                let char = if self.get(x, y) < 0 {
                     String::from('-')
                 } else {
-                    self.get(x, 14 - y).to_string()
+                    self.get(x, y).to_string()
                 };
                 write!(f, "{}", char)?;
             }
@@ -271,95 +534,42 @@ impl Debug for Board {
     }
 }
 
-impl Clone for Board {
-    fn clone(&self) -> Board {
+impl<const W: usize, const H: usize, const C: usize> Clone for Board<W, H, C> {
+    fn clone(&self) -> Board<W, H, C> {
         Board {
             score: self.score,
             color_counts: self.color_counts,
             board: self.board,
             actions: self.actions.clone(),
+            zobrist: self.zobrist,
+            rule: self.rule,
         }
     }
 }
 
-impl Hash for Board {
-    fn hash<H: Hasher>(&self, state: &mut H) {
-        self.board.hash(state);
-        self.score.hash(state);
+impl<const W: usize, const H: usize, const C: usize> Hash for Board<W, H, C> {
+    fn hash<H2: Hasher>(&self, state: &mut H2) {
+        state.write_u64(self.zobrist);
     }
 }
 
-impl PartialEq for Board {
-    fn eq(&self, other: &Board) -> bool {
+impl<const W: usize, const H: usize, const C: usize> PartialEq for Board<W, H, C> {
+    fn eq(&self, other: &Board<W, H, C>) -> bool {
         self.board == other.board
     }
 }
 
-impl Eq for Board {}
+impl<const W: usize, const H: usize, const C: usize> Eq for Board<W, H, C> {}
 
 #[cfg(test)]
 mod tests {
     use std::hash::DefaultHasher;
 
     use super::*;
+    use crate::test_util;
 
     fn get_board(level: i32) -> Board {
-        let board = match level {
-            1 => [
-                [1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1],
-                [0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0],
-                [1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1],
-                [1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1],
-                [2, 2, 2, 2, 2, 2, 2, 2, 2, 2, 2, 2, 2, 2, 2],
-                [0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0],
-                [2, 2, 2, 2, 2, 2, 2, 2, 2, 2, 2, 2, 2, 2, 2],
-                [2, 2, 2, 2, 2, 2, 2, 2, 2, 2, 2, 2, 2, 2, 2],
-                [1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1],
-                [1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1],
-                [2, 2, 2, 2, 2, 2, 2, 2, 2, 2, 2, 2, 2, 2, 2],
-                [0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0],
-                [3, 3, 3, 3, 3, 3, 3, 3, 3, 3, 3, 3, 3, 3, 3],
-                [1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1],
-                [4, 4, 4, 4, 4, 4, 4, 4, 4, 4, 4, 4, 4, 4, 4],
-            ],
-            2 => [
-                [1, 2, 2, 0, 0, 1, 1, 4, 3, 3, 4, 1, 1, 2, 0],
-                [1, 2, 2, 0, 0, 1, 1, 4, 3, 3, 4, 1, 1, 2, 0],
-                [1, 2, 2, 0, 0, 1, 1, 4, 3, 3, 4, 1, 1, 2, 0],
-                [1, 2, 2, 0, 0, 1, 1, 4, 3, 3, 4, 1, 1, 2, 0],
-                [1, 2, 2, 0, 0, 1, 1, 4, 3, 3, 4, 1, 1, 2, 0],
-                [1, 2, 2, 0, 0, 1, 1, 4, 3, 3, 4, 1, 1, 2, 0],
-                [1, 2, 2, 0, 0, 1, 1, 4, 3, 3, 4, 1, 1, 2, 0],
-                [1, 2, 2, 0, 0, 1, 1, 4, 3, 3, 4, 1, 1, 2, 0],
-                [1, 2, 2, 0, 0, 1, 1, 4, 3, 3, 4, 1, 1, 2, 0],
-                [1, 2, 2, 0, 0, 1, 1, 4, 3, 3, 4, 1, 1, 2, 0],
-                [1, 2, 2, 0, 0, 1, 1, 4, 3, 3, 4, 1, 1, 2, 0],
-                [1, 2, 2, 0, 0, 1, 1, 4, 3, 3, 4, 1, 1, 2, 0],
-                [1, 2, 2, 0, 0, 1, 1, 4, 3, 3, 4, 1, 1, 2, 0],
-                [1, 2, 2, 0, 0, 1, 1, 4, 3, 3, 4, 1, 1, 2, 0],
-                [1, 2, 2, 0, 0, 1, 1, 4, 3, 3, 4, 1, 1, 2, 0],
-            ],
-            3 => [
-                [2, 2, 2, 0, 0, 0, 0, 0, 0, 1, 1, 1, 2, 2, 2],
-                [2, 2, 2, 0, 0, 0, 0, 0, 0, 1, 1, 1, 2, 2, 2],
-                [2, 2, 2, 0, 0, 0, 0, 0, 0, 1, 1, 1, 2, 2, 2],
-                [4, 4, 4, 1, 1, 1, 3, 3, 3, 2, 2, 2, 0, 0, 0],
-                [4, 4, 4, 1, 1, 1, 3, 3, 3, 2, 2, 2, 0, 0, 0],
-                [4, 4, 4, 1, 1, 1, 3, 3, 3, 2, 2, 2, 0, 0, 0],
-                [0, 0, 0, 0, 0, 0, 4, 4, 4, 1, 1, 1, 4, 4, 4],
-                [0, 0, 0, 0, 0, 0, 4, 4, 4, 1, 1, 1, 4, 4, 4],
-                [0, 0, 0, 0, 0, 0, 4, 4, 4, 1, 1, 1, 4, 4, 4],
-                [1, 1, 1, 3, 3, 3, 2, 2, 2, 3, 3, 3, 0, 0, 0],
-                [1, 1, 1, 3, 3, 3, 2, 2, 2, 3, 3, 3, 0, 0, 0],
-                [1, 1, 1, 3, 3, 3, 2, 2, 2, 3, 3, 3, 0, 0, 0],
-                [4, 4, 4, 0, 0, 0, 3, 3, 3, 1, 1, 1, 2, 2, 2],
-                [4, 4, 4, 0, 0, 0, 3, 3, 3, 1, 1, 1, 2, 2, 2],
-                [4, 4, 4, 0, 0, 0, 3, 3, 3, 1, 1, 1, 2, 2, 2],
-            ],
-            _ => panic!("Invalid"),
-        };
-
-        Board::new(board)
+        Board::new(test_util::level(level))
     }
 
     #[test]
@@ -460,15 +670,82 @@ mod tests {
         assert_eq!(board.get(6, 12), -1);
     }
 
+    #[test]
+    fn test_try_play() {
+        let mut board = get_board(3);
+
+        assert_eq!(board.try_play(15, 0), Err(MoveError::OutOfBounds));
+        assert_eq!(board.get_actions().len(), 0);
+
+        assert_eq!(board.try_play(6, 11), Ok(()));
+        assert_eq!(board.get(6, 12), -1);
+        assert_eq!(board.try_play(6, 12), Err(MoveError::EmptyCell));
+        assert_eq!(board.get_actions().len(), 1);
+
+        let stale = board.compute_region(0, 0);
+        board.play_region(&stale);
+        assert_eq!(board.try_play_region(&stale), Err(MoveError::StaleRegion));
+
+        let mut cells = [[-1i8; 15]; 15];
+        cells[0][0] = 0;
+        cells[0][1] = 1;
+        cells[1][0] = 1;
+        let mut single: Board = Board::new(cells);
+        assert_eq!(single.try_play(0, 0), Err(MoveError::GameOver));
+
+        cells[0][2] = 1;
+        let mut single: Board = Board::new(cells);
+        assert_eq!(single.try_play(0, 0), Err(MoveError::SingletonRegion));
+    }
+
+    #[test]
+    fn test_try_play_stale_region() {
+        let mut cells = [[-1i8; 15]; 15];
+        cells[0][..3].copy_from_slice(&[0, 0, 0]);
+        let board: Board = Board::new(cells);
+        let region = board.compute_region(0, 0);
+
+        // the same cells in another color
+        cells[0][..3].copy_from_slice(&[1, 1, 1]);
+        let mut recolored: Board = Board::new(cells);
+        assert_eq!(
+            recolored.try_play_region(&region),
+            Err(MoveError::StaleRegion)
+        );
+
+        // a legal looking part of the group
+        let mut partial = board.compute_region(0, 0);
+        partial
+            .positions
+            .retain(|&index| index != <Board>::get_index(2, 0));
+        let mut board = board;
+        assert_eq!(board.try_play_region(&partial), Err(MoveError::StaleRegion));
+        assert_eq!(board.get_actions().len(), 0);
+
+        assert_eq!(board.try_play_region(&region), Ok(()));
+    }
+
+    #[test]
+    fn test_legal_moves() {
+        let board = get_board(2);
+
+        assert!(board.legal_moves().all(|region| region.len() >= 2));
+        assert_eq!(board.legal_moves().count(), 10);
+        assert_eq!(
+            board.legal_moves().next().unwrap().first_position,
+            <Board>::get_index(0, 0)
+        );
+    }
+
     #[test]
     fn test_conversion() {
         for x in 0..16 {
             for y in 0..16 {
-                let idx = Board::get_index(x, y);
+                let idx = <Board>::get_index(x, y);
 
                 assert_eq!(idx, x + y * 16);
 
-                let (x2, y2) = Board::to_coordinates(&idx);
+                let (x2, y2) = <Board>::to_coordinates(&idx);
                 assert_eq!(x, x2);
                 assert_eq!(y, y2);
             }
@@ -496,4 +773,179 @@ mod tests {
         assert_eq!(hash_value1, hash_value2);
         assert_ne!(hash_value1, hash_value3);
     }
+
+    #[test]
+    fn test_unplay() {
+        let mut board = get_board(3);
+        let mut history = vec![];
+
+        loop {
+            let all_regions = board.compute_all_regions();
+            if all_regions.is_empty() {
+                break;
+            }
+            let region = &all_regions[all_regions.len() / 2];
+            history.push((board.clone(), board.play_region_with_undo(region)));
+        }
+        assert!(board.get_score() > 0);
+
+        while let Some((previous, undo)) = history.pop() {
+            board.unplay(undo);
+
+            assert!(board == previous);
+            assert_eq!(board.score, previous.score);
+            assert_eq!(board.color_counts, previous.color_counts);
+            assert_eq!(board.zobrist(), previous.zobrist());
+            assert_eq!(board.get_actions_str(), previous.get_actions_str());
+        }
+    }
+
+    #[test]
+    fn test_zobrist() {
+        let mut board = get_board(3);
+
+        for (x, y) in [(0, 14), (6, 11), (6, 11), (3, 5), (0, 8), (6, 5)] {
+            board.play(x, y);
+
+            let mut cells = [[-1; 15]; 15];
+            for (y, row) in cells.iter_mut().enumerate() {
+                for (x, cell) in row.iter_mut().enumerate() {
+                    *cell = board.get(x, y);
+                }
+            }
+            assert_eq!(board.zobrist(), <Board>::new(cells).zobrist());
+        }
+
+        assert_ne!(board.zobrist(), get_board(3).zobrist());
+    }
+
+    #[test]
+    fn test_other_sizes() {
+        // vertical stripes of 2 columns, colors 0 to 2
+        let mut cells = [[0i8; 20]; 10];
+        for row in cells.iter_mut() {
+            for (x, cell) in row.iter_mut().enumerate() {
+                *cell = ((x / 2) % 3) as i8;
+            }
+        }
+        let mut board: Board<20, 10, 3> = Board::new(cells);
+
+        assert_eq!(Board::<20, 10, 3>::to_coordinates(&(3 * 32 + 19)), (19, 3));
+        assert_eq!(board.get_color_counts(), [80, 60, 60]);
+        assert_eq!(board.compute_all_regions().len(), 10);
+
+        board.play(19, 9);
+        assert_eq!(board.get_score(), 18 * 18);
+        assert_eq!(board.get(18, 0), -1);
+        assert_eq!(board.get(17, 0), 2);
+
+        let mut history = vec![];
+        while let Some(region) = board.legal_moves().last() {
+            history.push((board.clone(), board.play_region_with_undo(&region)));
+        }
+        assert_eq!(board.get_color_counts(), [0, 0, 0]);
+        assert_eq!(board.zobrist(), 0);
+        while let Some((previous, undo)) = history.pop() {
+            board.unplay(undo);
+            assert!(board == previous);
+            assert_eq!(board.zobrist(), previous.zobrist());
+        }
+
+        let mut small: Board<3, 2, 2> = Board::new([[0, 1, 1], [0, 0, 1]]);
+        assert_eq!(small.compute_all_regions().len(), 2);
+        small.play(0, 0);
+        assert_eq!(small.get(0, 0), 1);
+        assert_eq!(small.get(2, 0), -1);
+        assert_eq!(small.try_play(0, 2), Err(MoveError::OutOfBounds));
+    }
+
+    #[test]
+    fn test_scoring_rules() {
+        let left: [[i8; 4]; 1] = [[0, 0, 1, 2]];
+        let clear: [[i8; 4]; 1] = [[0, 0, 1, 1]];
+
+        for (rule, left_score, clear_score) in [
+            (ScoringRule::CodinGame, 0, 1000),
+            (ScoringRule::JsGames, -2, 1000),
+            (ScoringRule::Clickomania, 2, 4),
+        ] {
+            let mut board: Board<4, 1, 3> = Board::with_rule(left, rule);
+            let undo = board.play_region_with_undo(&board.compute_region(0, 0));
+            assert_eq!(board.get_score(), left_score, "{}", rule);
+            board.unplay(undo);
+            assert_eq!(board.get_score(), 0, "{}", rule);
+
+            let mut board: Board<4, 1, 3> = Board::with_rule(clear, rule);
+            board.play(0, 0);
+            board.play(0, 0);
+            assert_eq!(board.get_score(), clear_score, "{}", rule);
+        }
+    }
+
+    #[test]
+    fn test_terminal() {
+        let mut board = get_board(3);
+        assert!(!board.is_terminal());
+        assert_eq!(board.final_score(), None);
+        assert_eq!(board.remaining_cells(), 225);
+
+        while let Some(region) = board.legal_moves().last() {
+            assert!(!board.is_terminal());
+            board.play_region(&region);
+        }
+        assert!(board.is_terminal());
+        let cells = (0..15)
+            .flat_map(|y| (0..15).map(move |x| (x, y)))
+            .filter(|&(x, y)| board.get(x, y) >= 0)
+            .count();
+        assert_eq!(board.remaining_cells(), cells);
+
+        let breakdown = board.final_score().unwrap();
+        assert_eq!(breakdown.total(), board.get_score());
+        assert_eq!(breakdown.leftover_penalty, 0);
+
+        let mut board: Board<4, 1, 3> = Board::with_rule([[0, 0, 1, 2]], ScoringRule::JsGames);
+        board.play(0, 0);
+        assert_eq!(board.remaining_cells(), 2);
+        assert_eq!(
+            board.final_score(),
+            Some(ScoreBreakdown {
+                region_points: 0,
+                clear_bonus: 0,
+                leftover_penalty: -2
+            })
+        );
+
+        let mut board: Board<4, 1, 3> = Board::new([[0, 0, 1, 1]]);
+        board.play(0, 0);
+        board.play(0, 0);
+        assert!(board.is_terminal());
+        assert_eq!(board.final_score().unwrap().clear_bonus, 1000);
+    }
+
+    #[test]
+    fn test_upper_bound() {
+        for rule in [
+            ScoringRule::CodinGame,
+            ScoringRule::JsGames,
+            ScoringRule::Clickomania,
+        ] {
+            for level in [1, 2, 3] {
+                let mut board = get_board(level);
+                board.rule = rule;
+
+                // every position of a game is bounded by the final score
+                let mut bounds = vec![];
+                while let Some(region) = board.legal_moves().last() {
+                    bounds.push(board.get_score() + board.upper_bound());
+                    board.play_region(&region);
+                }
+                assert_eq!(board.upper_bound(), 0);
+                assert!(bounds.iter().all(|&bound| bound >= board.get_score()));
+            }
+        }
+
+        let board: Board<4, 1, 3> = Board::new([[0, 0, 1, 1]]);
+        assert_eq!(board.upper_bound(), 1000);
+    }
 }
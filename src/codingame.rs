@@ -0,0 +1,204 @@
+use std::{
+    collections::VecDeque,
+    io::{self, BufRead, Write},
+};
+
+use crate::{
+    board::Board,
+    input,
+    solver::{Budget, Solver},
+};
+
+// CodinGame allows 20s for the first turn and 50ms for the others
+pub const FIRST_TURN_SECS: f64 = 19.0;
+pub const MOVE_MILLIS: u64 = 40;
+
+/// Keeps the plan found on the first turn and replays it, checking that the
+/// referee's board is the one we expect before each move.
+pub struct Player {
+    solver: Box<dyn Solver>,
+    iterations: Option<usize>,
+    first_turn_secs: f64,
+    move_millis: u64,
+    plan: VecDeque<(usize, usize)>,
+    predicted: Option<Board>,
+}
+
+impl Player {
+    pub fn new(
+        solver: Box<dyn Solver>,
+        iterations: Option<usize>,
+        first_turn_secs: f64,
+        move_millis: u64,
+    ) -> Player {
+        Player {
+            solver,
+            iterations,
+            first_turn_secs,
+            move_millis,
+            plan: VecDeque::new(),
+            predicted: None,
+        }
+    }
+
+    pub fn play_turn(&mut self, board: Board) -> (usize, usize) {
+        let budget = match self.predicted {
+            None => Budget::from_secs(self.first_turn_secs),
+            Some(_) => Budget::from_millis(self.move_millis),
+        }
+        .with_iterations(self.iterations);
+
+        let diverged = match self.predicted {
+            Some(ref predicted) => *predicted != board,
+            None => false,
+        };
+        if diverged {
+            eprintln!("Board differs from the prediction, re-planning");
+        }
+
+        if diverged || self.plan.is_empty() {
+            let solution = self.solver.solve(&board, &budget);
+            eprintln!("Planned score: {}", solution.score);
+            self.plan = match input::parse_actions(&solution.actions) {
+                Ok(actions) => actions.into(),
+                Err(e) => {
+                    eprintln!("Invalid plan: {}", e);
+                    VecDeque::new()
+                }
+            };
+        }
+
+        // without a plan any legal move keeps the game going
+        let action = self.plan.pop_front().unwrap_or_else(|| {
+            board.legal_moves().next().map_or((0, 0), |region| {
+                <Board>::to_coordinates(&region.first_position)
+            })
+        });
+        let mut predicted = board;
+        predicted.play(action.0, action.1);
+        self.predicted = Some(predicted);
+
+        action
+    }
+}
+
+pub fn run<R: BufRead, W: Write>(player: &mut Player, input: R, mut output: W) -> io::Result<()> {
+    let mut lines = input.lines();
+
+    loop {
+        let mut turn = Vec::with_capacity(15);
+        for line in lines.by_ref().take(15) {
+            turn.push(line?);
+        }
+        if turn.len() < 15 {
+            return Ok(());
+        }
+
+        let cells = input::parse_grid(&turn.join("\n"), 5)
+            .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e.to_string()))?;
+        let (x, y) = player.play_turn(Board::new(cells));
+        writeln!(output, "{} {}", x, y)?;
+        output.flush()?;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::solver::get_solver;
+
+    const TURN: &str = "0 0 1 1 2 2 3 3 4 4 0 0 1 1 2
+0 0 1 1 2 2 3 3 4 4 0 0 1 1 2
+1 1 2 2 3 3 4 4 0 0 1 1 2 2 3
+1 1 2 2 3 3 4 4 0 0 1 1 2 2 3
+2 2 3 3 4 4 0 0 1 1 2 2 3 3 4
+2 2 3 3 4 4 0 0 1 1 2 2 3 3 4
+3 3 4 4 0 0 1 1 2 2 3 3 4 4 0
+3 3 4 4 0 0 1 1 2 2 3 3 4 4 0
+4 4 0 0 1 1 2 2 3 3 4 4 0 0 1
+4 4 0 0 1 1 2 2 3 3 4 4 0 0 1
+0 0 1 1 2 2 3 3 4 4 0 0 1 1 2
+0 0 1 1 2 2 3 3 4 4 0 0 1 1 2
+1 1 2 2 3 3 4 4 0 0 1 1 2 2 3
+1 1 2 2 3 3 4 4 0 0 1 1 2 2 3
+2 2 3 3 4 4 0 0 1 1 2 2 3 3 4";
+
+    fn get_player() -> Player {
+        Player::new(get_solver("mcrws").unwrap(), Some(2), 1.0, 40)
+    }
+
+    #[test]
+    fn test_follow_plan() {
+        let mut player = get_player();
+        let board = Board::new(input::string_to_2d_array(TURN));
+
+        player.play_turn(board);
+        let planned = player.plan.clone();
+        let next = player.predicted.clone().unwrap();
+
+        assert_eq!(player.play_turn(next), planned[0]);
+        assert_eq!(player.plan.len(), planned.len() - 1);
+    }
+
+    #[test]
+    fn test_replan_on_divergence() {
+        let mut player = get_player();
+        let board = Board::new(input::string_to_2d_array(TURN));
+
+        player.play_turn(board.clone());
+        player.plan.clear();
+        player.plan.push_back((14, 14));
+
+        // the referee sends the initial board again instead of the predicted one
+        player.play_turn(board);
+
+        assert_ne!(player.plan.front(), Some(&(14, 14)));
+    }
+
+    #[test]
+    fn test_run() {
+        let mut player = get_player();
+        let mut output = Vec::new();
+
+        run(&mut player, TURN.as_bytes(), &mut output).unwrap();
+
+        let output = String::from_utf8(output).unwrap();
+        assert_eq!(output.lines().count(), 1);
+        let board: Board = Board::new(input::string_to_2d_array(TURN));
+        let (x, y) = input::parse_actions(output.trim()).unwrap()[0];
+        assert!(board.compute_region(x, y).len() >= 2);
+
+        let malformed = TURN.replacen("0 0 1", "0 x 1", 1);
+        assert!(run(&mut player, malformed.as_bytes(), Vec::new()).is_err());
+    }
+
+    /// Solver whose plans are always empty.
+    struct NoPlan;
+
+    impl Solver for NoPlan {
+        fn name(&self) -> &'static str {
+            "noplan"
+        }
+
+        fn configure(&mut self, _: &crate::solver::Parameters) {}
+
+        fn solve(&mut self, _: &Board, _: &Budget) -> crate::solver::Solution {
+            crate::solver::Solution {
+                actions: String::new(),
+                score: 0,
+            }
+        }
+    }
+
+    #[test]
+    fn test_empty_plan() {
+        let mut player = Player::new(Box::new(NoPlan), None, 1.0, 40);
+        let mut cells = [[-1i8; 15]; 15];
+        cells[0][0] = 1;
+        cells[0][2] = 3;
+        cells[0][3] = 3;
+
+        // (0, 0) is a single cell, the legal move is the pair of 3s
+        assert_eq!(player.play_turn(Board::new(cells)), (2, 0));
+    }
+}
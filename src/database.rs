@@ -0,0 +1,306 @@
+use std::time::Duration;
+
+use rusqlite::{params, Connection, OptionalExtension, Result};
+
+use crate::{input::TestCase, solver::Solution};
+
+pub const DATABASE: &str = "my_database.db";
+
+/// Stored in `PRAGMA user_version`; 0 is a new database or one with the old
+/// `my_objects` table.
+const SCHEMA_VERSION: i32 = 1;
+
+/// Version of the code stored with each run: the crate version and the commit
+/// it was built from, see `build.rs`.
+pub const VERSION: &str = concat!(env!("CARGO_PKG_VERSION"), "+", env!("GIT_HASH"));
+
+/// What produced a solution.
+pub struct Run<'a> {
+    pub solver: &'a str,
+    /// `key=value` parameters as a JSON object
+    pub parameters: String,
+    pub seed: u64,
+    pub iterations: Option<usize>,
+    pub time_limit: Option<f64>,
+    pub duration: Duration,
+}
+
+/// Row of `solutions`: an action string and the score it was recorded with.
+pub struct StoredSolution {
+    pub id: i64,
+    pub actions: String,
+    pub score: i32,
+}
+
+pub fn open() -> Result<Connection> {
+    let conn = Connection::open(DATABASE)?;
+    migrate(&conn)?;
+    Ok(conn)
+}
+
+fn migrate(conn: &Connection) -> Result<()> {
+    // batch jobs open the database at the same time: the version is read
+    // again once the write lock is held
+    conn.execute_batch("BEGIN IMMEDIATE")?;
+    let version: i32 = conn.query_row("PRAGMA user_version", [], |row| row.get(0))?;
+
+    let result = if version < 1 {
+        migrate_to_1(conn)
+    } else {
+        Ok(())
+    };
+    match result {
+        Ok(()) => conn.execute_batch(&format!("PRAGMA user_version = {}; COMMIT", SCHEMA_VERSION)),
+        Err(e) => {
+            conn.execute_batch("ROLLBACK")?;
+            Err(e)
+        }
+    }
+}
+
+fn migrate_to_1(conn: &Connection) -> Result<()> {
+    conn.execute_batch(
+        "CREATE TABLE runs (
+            id          INTEGER PRIMARY KEY AUTOINCREMENT,
+            solver      TEXT NOT NULL,
+            parameters  TEXT NOT NULL,
+            seed        INTEGER,
+            iterations  INTEGER,
+            time_limit  REAL,
+            duration    REAL,
+            timestamp   TEXT DEFAULT CURRENT_TIMESTAMP,
+            version     TEXT
+        );
+        CREATE TABLE solutions (
+            id          INTEGER PRIMARY KEY AUTOINCREMENT,
+            run_id      INTEGER NOT NULL REFERENCES runs(id),
+            hash        INTEGER NOT NULL,
+            title       TEXT NOT NULL,
+            actions     TEXT NOT NULL,
+            score       INTEGER NOT NULL
+        );
+        CREATE INDEX solutions_hash ON solutions(hash, score);
+        CREATE VIEW best_solutions AS
+            SELECT solutions.hash, solutions.title, solutions.actions, solutions.score,
+                   runs.solver, runs.parameters, runs.seed, runs.timestamp, runs.version
+            FROM solutions JOIN runs ON runs.id = solutions.run_id
+            WHERE solutions.id = (
+                SELECT best.id FROM solutions AS best
+                WHERE best.hash = solutions.hash
+                ORDER BY best.score DESC, best.id
+                LIMIT 1
+            );",
+    )?;
+
+    let has_objects = conn
+        .query_row(
+            "SELECT name FROM sqlite_master WHERE type = 'table' AND name = 'my_objects'",
+            [],
+            |row| row.get::<_, String>(0),
+        )
+        .optional()?
+        .is_some();
+    if !has_objects {
+        return Ok(());
+    }
+
+    // the old rows only know their seed, when they were recorded after it was added
+    let has_seed: bool = conn.query_row(
+        "SELECT COUNT(*) > 0 FROM pragma_table_info('my_objects') WHERE name = 'seed'",
+        [],
+        |row| row.get(0),
+    )?;
+    let seed = if has_seed { "seed" } else { "NULL" };
+    conn.execute_batch(&format!(
+        "INSERT INTO runs (id, solver, parameters, seed, timestamp)
+            SELECT id, 'unknown', '{{}}', {}, NULL FROM my_objects;
+        INSERT INTO solutions (run_id, hash, title, actions, score)
+            SELECT id, hash, name, actions, score FROM my_objects ORDER BY id;
+        DROP TABLE my_objects;",
+        seed
+    ))
+}
+
+pub fn save_solution(
+    conn: &Connection,
+    testcase: &TestCase,
+    run: &Run,
+    solution: &Solution,
+) -> Result<()> {
+    // both rows or none, a run without its solution would be listed as a best
+    let transaction = conn.unchecked_transaction()?;
+    transaction.execute(
+        "INSERT INTO runs (solver, parameters, seed, iterations, time_limit, duration, version)
+            VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7)",
+        params![
+            run.solver,
+            run.parameters,
+            // SQLite integers are signed, the seed is stored with the same bits
+            run.seed as i64,
+            run.iterations.map(|iterations| iterations as i64),
+            run.time_limit,
+            run.duration.as_secs_f64(),
+            VERSION,
+        ],
+    )?;
+
+    transaction.execute(
+        "INSERT INTO solutions (run_id, hash, title, actions, score) VALUES (?1, ?2, ?3, ?4, ?5)",
+        params![
+            transaction.last_insert_rowid(),
+            testcase.hash,
+            testcase.title,
+            solution.actions,
+            solution.score
+        ],
+    )?;
+
+    transaction.commit()
+}
+
+pub fn load_solutions(conn: &Connection, hash: i64) -> Result<Vec<StoredSolution>> {
+    let mut statement =
+        conn.prepare("SELECT id, actions, score FROM solutions WHERE hash = ?1 ORDER BY id")?;
+    let rows = statement.query_map(params![hash], |row| {
+        Ok(StoredSolution {
+            id: row.get(0)?,
+            actions: row.get(1)?,
+            score: row.get(2)?,
+        })
+    })?;
+
+    rows.collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn get_testcase(hash: i64) -> TestCase {
+        TestCase {
+            board: [[0; 15]; 15],
+            is_test: true,
+            is_validator: false,
+            title: format!("Standard Testset {}", hash),
+            hash,
+        }
+    }
+
+    fn get_run() -> Run<'static> {
+        Run {
+            solver: "beam",
+            parameters: "{\"width\":\"10\"}".to_string(),
+            seed: u64::MAX,
+            iterations: None,
+            time_limit: Some(1.5),
+            duration: Duration::from_millis(1200),
+        }
+    }
+
+    #[test]
+    fn test_save_and_best() {
+        let conn = Connection::open_in_memory().unwrap();
+        migrate(&conn).unwrap();
+
+        for (hash, score) in [(1, 10), (1, 30), (2, 5), (1, 20)] {
+            let solution = Solution {
+                actions: format!("0 {}", score),
+                score,
+            };
+            save_solution(&conn, &get_testcase(hash), &get_run(), &solution).unwrap();
+        }
+
+        let scores: Vec<i32> = load_solutions(&conn, 1)
+            .unwrap()
+            .iter()
+            .map(|solution| solution.score)
+            .collect();
+        assert_eq!(scores, [10, 30, 20]);
+
+        let best: Vec<(i64, String, i32, i64)> = conn
+            .prepare("SELECT hash, actions, score, seed FROM best_solutions ORDER BY hash")
+            .unwrap()
+            .query_map([], |row| {
+                Ok((row.get(0)?, row.get(1)?, row.get(2)?, row.get(3)?))
+            })
+            .unwrap()
+            .collect::<Result<_>>()
+            .unwrap();
+        assert_eq!(
+            best,
+            [
+                (1, "0 30".to_string(), 30, -1),
+                (2, "0 5".to_string(), 5, -1)
+            ]
+        );
+
+        let version: String = conn
+            .query_row("SELECT version FROM runs LIMIT 1", [], |row| row.get(0))
+            .unwrap();
+        assert_eq!(version, VERSION);
+    }
+
+    #[test]
+    fn test_save_is_atomic() {
+        let conn = Connection::open_in_memory().unwrap();
+        migrate(&conn).unwrap();
+        // the solution insert fails, the run insert must be rolled back
+        conn.execute_batch(
+            "DROP INDEX solutions_hash; DROP VIEW best_solutions; DROP TABLE solutions;",
+        )
+        .unwrap();
+
+        let solution = Solution {
+            actions: "0 0".to_string(),
+            score: 1,
+        };
+        assert!(save_solution(&conn, &get_testcase(1), &get_run(), &solution).is_err());
+
+        let runs: i64 = conn
+            .query_row("SELECT COUNT(*) FROM runs", [], |row| row.get(0))
+            .unwrap();
+        assert_eq!(runs, 0);
+    }
+
+    #[test]
+    fn test_migrate_my_objects() {
+        let conn = Connection::open_in_memory().unwrap();
+        conn.execute_batch(
+            "CREATE TABLE my_objects (
+                id INTEGER PRIMARY KEY AUTOINCREMENT,
+                name TEXT NOT NULL,
+                actions TEXT NOT NULL,
+                hash INTEGER NOT NULL,
+                score INTEGER NOT NULL
+            );
+            INSERT INTO my_objects (name, actions, hash, score) VALUES ('a', '0 0', 7, 12);
+            INSERT INTO my_objects (name, actions, hash, score) VALUES ('a', '1 0', 7, 15);",
+        )
+        .unwrap();
+
+        migrate(&conn).unwrap();
+        // a second call finds the current version and does nothing
+        migrate(&conn).unwrap();
+
+        let solutions = load_solutions(&conn, 7).unwrap();
+        assert_eq!(solutions.len(), 2);
+        assert_eq!(solutions[1].actions, "1 0");
+        assert_eq!(solutions[1].score, 15);
+
+        let (solver, score): (String, i32) = conn
+            .query_row("SELECT solver, score FROM best_solutions", [], |row| {
+                Ok((row.get(0)?, row.get(1)?))
+            })
+            .unwrap();
+        assert_eq!((solver.as_str(), score), ("unknown", 15));
+
+        let tables: i64 = conn
+            .query_row(
+                "SELECT COUNT(*) FROM sqlite_master WHERE name = 'my_objects'",
+                [],
+                |row| row.get(0),
+            )
+            .unwrap();
+        assert_eq!(tables, 0);
+    }
+}
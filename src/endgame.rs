@@ -0,0 +1,233 @@
+use lru::LruCache;
+use std::{cmp::Reverse, num::NonZeroUsize};
+
+use crate::{
+    board::Board,
+    game::Game,
+    input,
+    scoring::ScoringRule,
+    solver::{Budget, Parameters, Solution, Solver},
+};
+
+/// Positions with fewer cells left are finished by `Endgame::solve`.
+pub const ENDGAME_CELLS: usize = 20;
+const TABLE_SIZE: usize = 100000;
+/// Positions searched by one `Endgame::solve` before it gives up.
+const MAX_NODES: usize = 50000;
+// the clock is read at the first position, then every that many positions
+const NODES_PER_CHECK: usize = 1024;
+
+/// Exact solver for the end of a game: a depth-first search over every move,
+/// skipping the moves whose `Board::upper_bound` cannot beat the best finish
+/// found so far, with a transposition table of the best finish of each
+/// position. Only the cells and the rule matter to the rest of a game, so
+/// positions are keyed by both and store the points still to be scored and
+/// the first move to reach them; the table is kept from one solve to the next.
+pub struct Endgame {
+    threshold: usize,
+    table: LruCache<(ScoringRule, u64), (i32, usize)>,
+}
+
+impl Default for Endgame {
+    fn default() -> Endgame {
+        Endgame::new(ENDGAME_CELLS)
+    }
+}
+
+impl Endgame {
+    pub fn new(threshold: usize) -> Endgame {
+        Endgame {
+            threshold,
+            table: LruCache::new(NonZeroUsize::new(TABLE_SIZE).unwrap()),
+        }
+    }
+
+    /// Reads the `endgame` parameter, the threshold in cells; 0 disables it.
+    pub fn configure(&mut self, parameters: &Parameters) {
+        self.threshold = parameters.get("endgame", self.threshold);
+    }
+
+    pub fn applies<const W: usize, const H: usize, const C: usize, G: Game<W, H, C>>(
+        &self,
+        board: &G,
+    ) -> bool {
+        board.remaining_cells() < self.threshold
+    }
+
+    /// The best finish of `board`, or `None` when it has too many cells left
+    /// or when the search runs out of `budget` or of its `MAX_NODES`
+    /// positions first.
+    pub fn solve<const W: usize, const H: usize, const C: usize>(
+        &mut self,
+        board: &Board<W, H, C>,
+        budget: &Budget,
+    ) -> Option<Board<W, H, C>> {
+        if !self.applies(board) {
+            return None;
+        }
+
+        let mut board = board.clone();
+        let mut nodes = 0;
+        while !board.is_terminal() {
+            // the entry of the position is the last one written by `search`
+            self.search(&mut board, budget, &mut nodes)?;
+            let (_, index) = *self.table.get(&(board.rule(), board.zobrist())).unwrap();
+            board.play_index(index);
+        }
+
+        Some(board)
+    }
+
+    /// Points still to be scored from `board` with the best finish, `None`
+    /// when the search was stopped: only complete searches are stored.
+    fn search<const W: usize, const H: usize, const C: usize>(
+        &mut self,
+        board: &mut Board<W, H, C>,
+        budget: &Budget,
+        nodes: &mut usize,
+    ) -> Option<i32> {
+        let key = (board.rule(), board.zobrist());
+        if let Some(&(points, _)) = self.table.get(&key) {
+            return Some(points);
+        }
+
+        if *nodes >= MAX_NODES || (nodes.is_multiple_of(NODES_PER_CHECK) && budget.is_expired()) {
+            return None;
+        }
+        *nodes += 1;
+
+        let mut regions = board.compute_all_regions();
+        if regions.is_empty() {
+            return Some(0);
+        }
+        // large regions first, the first finish found is then a good one
+        regions.sort_by_key(|region| Reverse(region.len()));
+
+        let bound = board.upper_bound();
+        let mut best = (i32::MIN, regions[0].first_position);
+        for region in regions.iter() {
+            let score = board.get_score();
+            let undo = board.play_region_with_undo(region);
+            let delta = board.get_score() - score;
+            // the bound of a child only prunes it, so every stored value is exact
+            let points = if delta + board.upper_bound() > best.0 {
+                self.search(board, budget, nodes)
+                    .map(|points| delta + points)
+            } else {
+                Some(i32::MIN)
+            };
+            board.unplay(undo);
+
+            let points = points?;
+            if points > best.0 {
+                best = (points, region.first_position);
+            }
+            if best.0 >= bound {
+                break;
+            }
+        }
+
+        self.table.put(key, best);
+        Some(best.0)
+    }
+}
+
+/// Runs a solver, then replaces the end of its game by the exact finish of
+/// the first position with fewer cells left than the endgame threshold. The
+/// solvers already finish their rollouts or leaves with the endgame, this is
+/// a safety net, within what is left of the budget.
+pub struct WithEndgame<const W: usize, const H: usize, const C: usize> {
+    solver: Box<dyn Solver<W, H, C>>,
+    endgame: Endgame,
+}
+
+impl<const W: usize, const H: usize, const C: usize> WithEndgame<W, H, C> {
+    pub fn new(solver: Box<dyn Solver<W, H, C>>) -> WithEndgame<W, H, C> {
+        WithEndgame {
+            solver,
+            endgame: Endgame::default(),
+        }
+    }
+}
+
+impl<const W: usize, const H: usize, const C: usize> Solver<W, H, C> for WithEndgame<W, H, C> {
+    fn name(&self) -> &'static str {
+        self.solver.name()
+    }
+
+    fn configure(&mut self, parameters: &Parameters) {
+        self.solver.configure(parameters);
+        self.endgame.configure(parameters);
+    }
+
+    fn solve(&mut self, initial_state: &Board<W, H, C>, budget: &Budget) -> Solution {
+        let solution = self.solver.solve(initial_state, budget);
+
+        let mut board = initial_state.clone();
+        for (x, y) in input::parse_actions(&solution.actions).unwrap_or_default() {
+            if self.endgame.applies(&board) {
+                break;
+            }
+            board.play(x, y);
+        }
+
+        match self.endgame.solve(&board, budget) {
+            Some(finish) if finish.get_score() > solution.score => {
+                eprintln!(
+                    "Endgame: {} instead of {} from {} cells",
+                    finish.get_score(),
+                    solution.score,
+                    board.remaining_cells()
+                );
+                Solution::from_board(&finish)
+            }
+            _ => solution,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Best score from `board` by trying every sequence of moves.
+    fn brute_force<const W: usize, const H: usize, const C: usize>(board: &Board<W, H, C>) -> i32 {
+        board
+            .compute_all_regions()
+            .iter()
+            .map(|region| {
+                let mut copy = board.clone();
+                copy.play_region(region);
+                brute_force(&copy)
+            })
+            .max()
+            .unwrap_or(board.get_score())
+    }
+
+    #[test]
+    fn test_solve() {
+        let cells = [[0, 1, 1, 2, 0, 1], [2, 0, 1, 0, 2, 2], [1, 2, 0, 0, 1, 0]];
+
+        // one table for every rule: the rule is part of the key
+        let mut endgame = Endgame::new(ENDGAME_CELLS);
+        for rule in ScoringRule::NAMES {
+            let board: Board<6, 3, 3> = Board::with_rule(cells, rule.parse().unwrap());
+            let finish = endgame.solve(&board, &Budget::default()).unwrap();
+
+            assert!(finish.is_terminal());
+            assert_eq!(finish.get_score(), brute_force(&board), "{}", rule);
+            let replayed = crate::verify::replay(&board, &finish.get_actions_str()).unwrap();
+            assert_eq!(replayed.get_score(), finish.get_score());
+        }
+
+        let board: Board<6, 3, 3> = Board::new(cells);
+        assert!(Endgame::new(18).solve(&board, &Budget::default()).is_none());
+
+        // a stopped search stores nothing, the next one is still exact
+        let mut endgame = Endgame::new(ENDGAME_CELLS);
+        assert!(endgame.solve(&board, &Budget::from_millis(0)).is_none());
+        assert!(endgame.table.is_empty());
+        let finish = endgame.solve(&board, &Budget::default()).unwrap();
+        assert_eq!(finish.get_score(), brute_force(&board));
+    }
+}
@@ -0,0 +1,248 @@
+use std::{fmt::Write, str::FromStr};
+
+use crate::{board::Board, database::StoredSolution, input::TestCase, verify};
+
+pub enum Format {
+    Json,
+    Rust,
+}
+
+impl FromStr for Format {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Format, String> {
+        match s {
+            "json" => Ok(Format::Json),
+            "rust" => Ok(Format::Rust),
+            _ => Err(format!("Unknown format {}, expected json or rust", s)),
+        }
+    }
+}
+
+/// Which testcases to export; with neither `tests` nor `validators` set both
+/// kinds are kept.
+#[derive(Default)]
+pub struct Filter {
+    pub title_prefix: Option<String>,
+    pub tests: bool,
+    pub validators: bool,
+}
+
+impl Filter {
+    pub fn accepts(&self, testcase: &TestCase) -> bool {
+        let title = self
+            .title_prefix
+            .as_ref()
+            .is_none_or(|prefix| testcase.title.starts_with(prefix.as_str()));
+        let kind = (!self.tests && !self.validators)
+            || (self.tests && testcase.is_test)
+            || (self.validators && testcase.is_validator);
+        title && kind
+    }
+}
+
+pub struct Entry {
+    pub hash: i64,
+    /// `Board::zobrist` of the initial board, which a submission can compute
+    pub zobrist: u64,
+    pub title: String,
+    pub actions: String,
+    pub score: i32,
+}
+
+/// Best of the stored solutions that replays legally to its recorded score.
+pub fn best_verified(testcase: &TestCase, mut solutions: Vec<StoredSolution>) -> Option<Entry> {
+    let board: Board = Board::new(testcase.board);
+    solutions.sort_by_key(|solution| std::cmp::Reverse(solution.score));
+
+    solutions
+        .into_iter()
+        .find_map(|solution| match verify::replay(&board, &solution.actions) {
+            Ok(replayed) if replayed.get_score() == solution.score => Some(Entry {
+                hash: testcase.hash,
+                zobrist: board.zobrist(),
+                title: testcase.title.clone(),
+                actions: solution.actions,
+                score: solution.score,
+            }),
+            Ok(replayed) => {
+                eprintln!(
+                    "{}: row {} scores {}, recorded {}",
+                    testcase.title,
+                    solution.id,
+                    replayed.get_score(),
+                    solution.score
+                );
+                None
+            }
+            Err(e) => {
+                eprintln!("{}: row {}: {}", testcase.title, solution.id, e);
+                None
+            }
+        })
+}
+
+/// Best entry of each hash, in the order the hashes first appear: a testset
+/// and its recolored copy share their hash, and their cells up to the colors.
+pub fn best_per_hash(entries: Vec<Entry>) -> Vec<Entry> {
+    let mut best: Vec<Entry> = Vec::new();
+    for entry in entries {
+        match best.iter_mut().find(|kept| kept.hash == entry.hash) {
+            Some(kept) if entry.score > kept.score => *kept = entry,
+            Some(_) => {}
+            None => best.push(entry),
+        }
+    }
+    best
+}
+
+/// Same map as `get_result.py`: testcase hash to action string.
+pub fn to_json(entries: &[Entry]) -> String {
+    let map: serde_json::Map<String, serde_json::Value> = entries
+        .iter()
+        .map(|entry| (entry.hash.to_string(), entry.actions.clone().into()))
+        .collect();
+    serde_json::to_string_pretty(&map).unwrap()
+}
+
+/// Lookup function to paste into a submission, keyed by the Zobrist key of
+/// the first board since the testcase hash is not known during a game.
+pub fn to_rust(entries: &[Entry]) -> String {
+    let mut source = String::new();
+    source.push_str("/// Precomputed solution for the board whose `Board::zobrist()` is `hash`.\n");
+    source.push_str("pub fn lookup(hash: u64) -> Option<&'static str> {\n");
+    source.push_str("    match hash {\n");
+    for entry in entries {
+        writeln!(source, "        // {}: {}", entry.title, entry.score).unwrap();
+        writeln!(
+            source,
+            "        {:#018x} => Some({:?}),",
+            entry.zobrist, entry.actions
+        )
+        .unwrap();
+    }
+    source.push_str("        _ => None,\n");
+    source.push_str("    }\n");
+    source.push_str("}\n");
+    source
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_util;
+
+    fn get_testcase() -> TestCase {
+        TestCase {
+            board: test_util::blocks(),
+            is_test: false,
+            is_validator: true,
+            title: "Standard Testset 1".to_string(),
+            hash: 42,
+        }
+    }
+
+    #[test]
+    fn test_filter() {
+        let testcase = get_testcase();
+
+        assert!(Filter::default().accepts(&testcase));
+        assert!(Filter {
+            title_prefix: Some("Standard".to_string()),
+            validators: true,
+            ..Filter::default()
+        }
+        .accepts(&testcase));
+        assert!(!Filter {
+            tests: true,
+            ..Filter::default()
+        }
+        .accepts(&testcase));
+        assert!(!Filter {
+            title_prefix: Some("Vertical".to_string()),
+            ..Filter::default()
+        }
+        .accepts(&testcase));
+    }
+
+    #[test]
+    fn test_best_verified() {
+        let testcase = get_testcase();
+        let mut board: Board = Board::new(testcase.board);
+        board.play(0, 0);
+        let legal = board.get_actions_str();
+        let score = board.get_score();
+
+        let solutions = vec![
+            StoredSolution {
+                id: 1,
+                actions: legal.clone(),
+                score,
+            },
+            // recorded with a score it does not reach
+            StoredSolution {
+                id: 2,
+                actions: legal.clone(),
+                score: score + 100,
+            },
+            StoredSolution {
+                id: 3,
+                actions: "0 0;20 20".to_string(),
+                score: score + 200,
+            },
+        ];
+
+        let entry = best_verified(&testcase, solutions).unwrap();
+        assert_eq!(entry.actions, legal);
+        assert_eq!(entry.score, score);
+        assert_eq!(entry.zobrist, <Board>::new(testcase.board).zobrist());
+
+        assert!(to_json(&[entry]).contains(&format!("\"42\": \"{}\"", legal)));
+    }
+
+    #[test]
+    fn test_best_per_hash() {
+        let entry = |hash, title: &str, score| Entry {
+            hash,
+            zobrist: 0,
+            title: title.to_string(),
+            actions: String::new(),
+            score,
+        };
+        let entries = vec![
+            entry(1, "Standard Testset 1", 30),
+            entry(2, "Standard Testset 2", 20),
+            entry(1, "Standard Testset 1 (recolored)", 40),
+            entry(2, "Standard Testset 2 (recolored)", 10),
+        ];
+
+        let best = best_per_hash(entries);
+        let kept: Vec<(&str, i32)> = best
+            .iter()
+            .map(|entry| (entry.title.as_str(), entry.score))
+            .collect();
+        assert_eq!(
+            kept,
+            [
+                ("Standard Testset 1 (recolored)", 40),
+                ("Standard Testset 2", 20)
+            ]
+        );
+    }
+
+    #[test]
+    fn test_to_rust() {
+        let entry = Entry {
+            hash: 42,
+            zobrist: 0xabc,
+            title: "Standard Testset 1".to_string(),
+            actions: "0 0;1 2".to_string(),
+            score: 9,
+        };
+
+        let source = to_rust(&[entry]);
+        assert!(source.contains("        0x0000000000000abc => Some(\"0 0;1 2\"),\n"));
+        assert!(source.contains("        // Standard Testset 1: 9\n"));
+        assert!(source.ends_with("        _ => None,\n    }\n}\n"));
+    }
+}
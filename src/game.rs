@@ -0,0 +1,57 @@
+//! What a rollout needs from a board, so that a solver can play its
+//! simulations on `BitBoard` and keep `Board` for the rest of the search.
+
+use crate::{board::Board, region::Region};
+
+/// A backend for the games of a `W` x `H` board with `C` colors. Regions use
+/// the cell indices of `Board` whatever the backend, so that they can be
+/// played on either one.
+pub trait Game<const W: usize, const H: usize, const C: usize>: Clone {
+    /// The same position, score, rule and moves as `board`.
+    fn from_board(board: &Board<W, H, C>) -> Self;
+
+    /// The game as a `Board`, replaying on `start`, the board this game was
+    /// made from, the moves played since.
+    fn into_board(self, start: &Board<W, H, C>) -> Board<W, H, C>;
+
+    fn get_score(&self) -> i32;
+
+    /// Key of the cells, for the caches of regions.
+    fn zobrist(&self) -> u64;
+
+    fn remaining_cells(&self) -> usize;
+
+    fn compute_all_regions(&self) -> Vec<Region>;
+
+    fn play_region(&mut self, region: &Region);
+}
+
+impl<const W: usize, const H: usize, const C: usize> Game<W, H, C> for Board<W, H, C> {
+    fn from_board(board: &Board<W, H, C>) -> Board<W, H, C> {
+        board.clone()
+    }
+
+    fn into_board(self, _start: &Board<W, H, C>) -> Board<W, H, C> {
+        self
+    }
+
+    fn get_score(&self) -> i32 {
+        Board::get_score(self)
+    }
+
+    fn zobrist(&self) -> u64 {
+        Board::zobrist(self)
+    }
+
+    fn remaining_cells(&self) -> usize {
+        Board::remaining_cells(self)
+    }
+
+    fn compute_all_regions(&self) -> Vec<Region> {
+        Board::compute_all_regions(self)
+    }
+
+    fn play_region(&mut self, region: &Region) {
+        Board::play_region(self, region)
+    }
+}
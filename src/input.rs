@@ -1,6 +1,9 @@
-use std::{collections::HashMap, fs};
+use std::{
+    collections::HashMap,
+    fmt::{self, Display, Formatter},
+    fs,
+};
 
-use rusqlite::{params, Connection, Result};
 use serde::{Deserialize, Serialize};
 
 #[derive(Serialize, Deserialize)]
@@ -42,42 +45,187 @@ pub fn load_json(testcase: &str) -> TestCase {
     }
 }
 
-fn string_to_2d_array(input: &str) -> [[i8; 15]; 15] {
-    let mut board: [[i8; 15]; 15] = [[0; 15]; 15];
+pub fn string_to_2d_array(input: &str) -> [[i8; 15]; 15] {
+    parse_grid(input, 5).unwrap_or_else(|e| panic!("Invalid board: {}", e))
+}
+
+/// Why a grid of cells could not be parsed.
+#[derive(Debug, PartialEq)]
+pub enum GridError {
+    NotRectangular { row: usize },
+    Size { width: usize, height: usize },
+    InvalidCell { row: usize, column: usize },
+}
+
+impl Display for GridError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        match self {
+            GridError::NotRectangular { row } => {
+                write!(f, "row {} does not have the width of the first one", row)
+            }
+            GridError::Size { width, height } => {
+                write!(f, "unexpected size {}x{}", width, height)
+            }
+            GridError::InvalidCell { row, column } => {
+                write!(f, "invalid cell in row {}, column {}", row, column)
+            }
+        }
+    }
+}
+
+/// Width and height of a grid given as one line of space separated cells per
+/// row, top row first.
+pub fn grid_size(input: &str) -> Result<(usize, usize), GridError> {
+    let widths: Vec<usize> = input
+        .lines()
+        .filter(|line| !line.trim().is_empty())
+        .map(|line| line.split_whitespace().count())
+        .collect();
 
-    for (i, line) in input.lines().enumerate() {
-        for (j, num) in line.split_whitespace().enumerate() {
-            board[14 - i][j] = num.parse().unwrap();
+    let width = widths.first().copied().unwrap_or(0);
+    match widths.iter().position(|&w| w != width) {
+        Some(row) => Err(GridError::NotRectangular { row }),
+        None => Ok((width, widths.len())),
+    }
+}
+
+/// Number of colors of a grid: one more than its highest cell.
+pub fn grid_colors(input: &str) -> Result<usize, GridError> {
+    let rows = input.lines().filter(|line| !line.trim().is_empty());
+    let mut colors = 0;
+    for (i, line) in rows.enumerate() {
+        for (j, cell) in line.split_whitespace().enumerate() {
+            let color: i8 = cell
+                .parse()
+                .map_err(|_| GridError::InvalidCell { row: i, column: j })?;
+            colors = colors.max(color as isize + 1);
         }
     }
 
-    board
+    Ok(colors as usize)
+}
+
+/// Parses a `W` x `H` grid, top row first; cells are colors below `colors`,
+/// or negative for an empty cell.
+pub fn parse_grid<const W: usize, const H: usize>(
+    input: &str,
+    colors: usize,
+) -> Result<[[i8; W]; H], GridError> {
+    let (width, height) = grid_size(input)?;
+    if (width, height) != (W, H) {
+        return Err(GridError::Size { width, height });
+    }
+
+    let mut board = [[0; W]; H];
+    let rows = input.lines().filter(|line| !line.trim().is_empty());
+    for (i, line) in rows.enumerate() {
+        for (j, cell) in line.split_whitespace().enumerate() {
+            board[H - 1 - i][j] = cell
+                .parse()
+                .ok()
+                .filter(|&color: &i8| (color as isize) < colors as isize)
+                .ok_or(GridError::InvalidCell { row: i, column: j })?;
+        }
+    }
+
+    Ok(board)
+}
+
+/// An action that is not two coordinates; `step` counts the actions from 0.
+#[derive(Debug, PartialEq)]
+pub struct ActionError {
+    pub step: usize,
+    pub action: String,
+}
+
+impl Display for ActionError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "move {}: cannot parse {:?} as \"x y\"",
+            self.step, self.action
+        )
+    }
+}
+
+/// Parses the `"x y;x y;..."` format printed by `Board::get_actions_str`.
+pub fn parse_actions(actions: &str) -> Result<Vec<(usize, usize)>, ActionError> {
+    actions
+        .split(';')
+        .filter(|action| !action.trim().is_empty())
+        .enumerate()
+        .map(|(step, action)| {
+            let coordinates: Vec<usize> = action
+                .split_whitespace()
+                .map(|c| c.parse().ok())
+                .collect::<Option<_>>()
+                .unwrap_or_default();
+            match coordinates[..] {
+                [x, y] => Ok((x, y)),
+                _ => Err(ActionError {
+                    step,
+                    action: action.to_string(),
+                }),
+            }
+        })
+        .collect()
 }
 
 fn get_title(s: &JSONTestCase) -> String {
     s.title.get("1").unwrap().to_string()
 }
 
-pub fn save_to_db(testcase: &TestCase, solution: &String, score: u32) -> Result<()> {
-    // Connect to the SQLite database (or create it if it doesn't exist)
-    let conn = Connection::open("my_database.db")?;
-
-    // Create the table if it doesn't already exist
-    conn.execute(
-        "CREATE TABLE IF NOT EXISTS my_objects (
-                  id     INTEGER PRIMARY KEY AUTOINCREMENT,
-                  name   TEXT NOT NULL,
-                  actions TEXT NOT NULL,
-                  hash   INTEGER NOT NULL,
-                  score  INTEGER NOT NULL
-                  )",
-        [],
-    )?;
-
-    conn.execute(
-        "INSERT INTO my_objects (name, actions, hash, score) VALUES (?1, ?2, ?3, ?4)",
-        params![testcase.title, solution, testcase.hash, score],
-    )?;
-
-    Ok(())
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_actions() {
+        assert_eq!(parse_actions("1 2;3 4;"), Ok(vec![(1, 2), (3, 4)]));
+        assert_eq!(parse_actions(""), Ok(vec![]));
+        assert_eq!(
+            parse_actions("1 2;3"),
+            Err(ActionError {
+                step: 1,
+                action: "3".to_string()
+            })
+        );
+        assert_eq!(
+            parse_actions("1 x"),
+            Err(ActionError {
+                step: 0,
+                action: "1 x".to_string()
+            })
+        );
+    }
+
+    #[test]
+    fn test_parse_grid() {
+        let grid = "0 1 2\n-1 3 4\n";
+
+        assert_eq!(grid_size(grid), Ok((3, 2)));
+        assert_eq!(grid_colors(grid), Ok(5));
+        assert_eq!(grid_colors("-1 -1"), Ok(0));
+        assert_eq!(parse_grid::<3, 2>(grid, 5), Ok([[-1, 3, 4], [0, 1, 2]]));
+
+        assert_eq!(
+            parse_grid::<2, 3>(grid, 5),
+            Err(GridError::Size {
+                width: 3,
+                height: 2
+            })
+        );
+        assert_eq!(
+            parse_grid::<3, 2>(grid, 4),
+            Err(GridError::InvalidCell { row: 1, column: 2 })
+        );
+        assert_eq!(
+            parse_grid::<3, 2>("0 1 2\n0 x 2", 5),
+            Err(GridError::InvalidCell { row: 1, column: 1 })
+        );
+        assert_eq!(
+            grid_size("0 1 2\n0 1\n0 1 2"),
+            Err(GridError::NotRectangular { row: 1 })
+        );
+    }
 }
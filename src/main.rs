@@ -1,25 +1,478 @@
-use std::env;
+use std::{env, fs, io, path::Path, process, time::Instant};
 
 use board::Board;
+use scoring::ScoringRule;
+use solver::{Budget, Parameters, Solution, Solver};
 
+mod batch;
+mod bitboard;
 mod board;
+mod codingame;
+mod database;
+mod endgame;
+mod export;
+mod game;
 mod input;
 mod region;
+mod scoring;
 mod solver;
 mod solvers;
+#[cfg(test)]
+mod test_util;
+mod verify;
 
-fn main() {
-    let args: Vec<String> = env::args().collect();
-    let testcase = input::load_json(&args[1]);
+enum Mode {
+    Solve(String),
+    CodinGame,
+    Verify(String, Option<String>),
+    Batch(String),
+    Export(String),
+}
+
+struct Args {
+    mode: Mode,
+    solver: String,
+    iterations: Option<usize>,
+    time: Option<f64>,
+    move_time: Option<u64>,
+    jobs: usize,
+    seed: u64,
+    rule: ScoringRule,
+    format: export::Format,
+    filter: export::Filter,
+    parameters: Parameters,
+}
+
+fn usage(program: &str) -> String {
+    format!(
+        "Usage: {} <mode> [options] [key=value ...]
+
+Modes:
+    <testcase.json>                 solve a testcase
+    <grid.txt>                      solve a grid of {} with {} to {} colors,
+                                    one row of cells per line, top row first
+    codingame                       play on stdin and stdout
+    verify <testcase.json> [\"x y;x y;...\"]
+    batch <directory|pattern>
+    export <directory|pattern>
+
+Options:
+    --solver {}
+    --iterations N
+    --time SECONDS
+    --move-time MILLIS
+    --jobs N
+    --seed N
+    --rule {}
+    --format json|rust
+    --title PREFIX
+    --tests
+    --validators",
+        program,
+        grid_sizes(),
+        MIN_GRID_COLORS,
+        MAX_GRID_COLORS,
+        solver::SOLVERS.join("|"),
+        ScoringRule::NAMES.join("|")
+    )
+}
+
+fn parse_args(args: &[String]) -> Args {
+    let mut mode = None;
+    let mut positional = Vec::new();
+    let mut solver = solver::DEFAULT_SOLVER.to_string();
+    let mut iterations = None;
+    let mut time = None;
+    let mut move_time = None;
+    let mut jobs = 1;
+    let mut seed: Option<u64> = None;
+    let mut rule = ScoringRule::default();
+    let mut format = export::Format::Json;
+    let mut filter = export::Filter::default();
+    let mut parameters = Parameters::default();
+
+    let mut iter = args.iter().skip(1);
+    while let Some(arg) = iter.next() {
+        match arg.as_str() {
+            "--solver" => solver = iter.next().expect("Missing solver name").clone(),
+            "--iterations" => {
+                let value = iter.next().expect("Missing iteration count");
+                iterations = Some(value.parse().expect("Invalid iteration count"));
+            }
+            "--time" => {
+                let value = iter.next().expect("Missing time in seconds");
+                time = Some(value.parse::<f64>().expect("Invalid time in seconds"));
+            }
+            "--move-time" => {
+                let value = iter.next().expect("Missing time in milliseconds");
+                move_time = Some(value.parse().expect("Invalid time in milliseconds"));
+            }
+            "--jobs" => {
+                let value = iter.next().expect("Missing number of jobs");
+                jobs = value.parse().expect("Invalid number of jobs");
+            }
+            "--seed" => {
+                let value = iter.next().expect("Missing seed");
+                seed = Some(value.parse().expect("Invalid seed"));
+            }
+            "--rule" => {
+                let value = iter.next().expect("Missing scoring rule");
+                rule = value.parse().unwrap_or_else(|e| panic!("{}", e));
+            }
+            "--format" => {
+                let value = iter.next().expect("Missing export format");
+                format = value.parse().unwrap_or_else(|e| panic!("{}", e));
+            }
+            "--title" => {
+                let value = iter.next().expect("Missing title prefix");
+                filter.title_prefix = Some(value.clone());
+            }
+            "--tests" => filter.tests = true,
+            "--validators" => filter.validators = true,
+            "codingame" | "verify" | "batch" | "bench" | "export" => mode = Some(arg.as_str()),
+            _ if parameters.parse_pair(arg) => {}
+            _ => positional.push(arg.clone()),
+        }
+    }
+
+    let mode = match (mode, positional.first()) {
+        (Some("codingame"), _) => Some(Mode::CodinGame),
+        (Some("verify"), Some(path)) => {
+            Some(Mode::Verify(path.clone(), positional.get(1).cloned()))
+        }
+        (Some("batch" | "bench"), Some(pattern)) => Some(Mode::Batch(pattern.clone())),
+        (Some("export"), Some(pattern)) => Some(Mode::Export(pattern.clone())),
+        (None, Some(path)) => Some(Mode::Solve(path.clone())),
+        _ => None,
+    };
+
+    // every run has a seed, drawn here when none is given, so it can be replayed
+    let seed = seed
+        .or_else(|| parameters.get_opt("seed"))
+        .unwrap_or_else(rand::random);
+    parameters.insert("seed", &seed.to_string());
+    if rule != ScoringRule::default() {
+        parameters.insert("rule", rule.name());
+    }
+
+    Args {
+        mode: mode.unwrap_or_else(|| panic!("{}", usage(&args[0]))),
+        solver,
+        iterations,
+        time,
+        move_time,
+        jobs,
+        seed,
+        rule,
+        format,
+        filter,
+        parameters,
+    }
+}
 
-    let board = Board::new(testcase.board);
+/// Sizes and color counts of the grid files, the only ones compiled in.
+const GRID_SIZES: [(usize, usize); 2] = [(15, 15), (20, 10)];
+const MIN_GRID_COLORS: usize = 2;
+const MAX_GRID_COLORS: usize = 6;
+
+fn grid_sizes() -> String {
+    GRID_SIZES
+        .map(|(width, height)| format!("{}x{}", width, height))
+        .join(" or ")
+}
+
+fn get_solver<const W: usize, const H: usize, const C: usize>(
+    args: &Args,
+) -> Box<dyn Solver<W, H, C>> {
+    let mut solver = solver::get_solver(&args.solver).unwrap_or_else(|| {
+        panic!(
+            "Unknown solver {}, expected one of {}",
+            args.solver,
+            solver::SOLVERS.join(", ")
+        )
+    });
+    solver.configure(&args.parameters);
+    solver
+}
+
+fn get_budget(args: &Args) -> Budget {
+    match args.time {
+        Some(secs) => Budget::from_secs(secs),
+        None => Budget::default(),
+    }
+    .with_iterations(args.iterations)
+}
+
+fn solve_testcase(args: &Args, path: &Path) -> batch::Row {
+    // the clock starts now, so parsing the testcase counts against the time
+    let start = Instant::now();
+    let budget = get_budget(args);
+
+    let testcase = input::load_json(path.to_str().unwrap());
+    let mut solver: Box<dyn Solver> = get_solver(args);
+    eprintln!(
+        "{} with {} and seed {} (test: {}, validator: {})",
+        testcase.title,
+        solver.name(),
+        args.seed,
+        testcase.is_test,
+        testcase.is_validator
+    );
+
+    let board: Board = Board::with_rule(testcase.board, args.rule);
     eprintln!("{:?}", board);
 
-    let (actions, score) = solver::solve(&board);
-    println!("{}", actions);
+    let solution = solver.solve(&board, &budget);
+    let duration = start.elapsed();
+    let row = batch::Row {
+        title: testcase.title.clone(),
+        hash: testcase.hash,
+        actions: solution.actions.clone(),
+        score: solution.score,
+        bound: board.upper_bound(),
+        duration,
+    };
+    eprintln!(
+        "{}: {} in {:.2}s, {:.1}% below the bound {}",
+        row.title,
+        row.score,
+        duration.as_secs_f64(),
+        row.gap(),
+        row.bound
+    );
 
-    match input::save_to_db(&testcase, &actions, score) {
-        Ok(_) => eprintln!("Row inserted successfully!"),
-        Err(e) => eprintln!("Error: {:?}", e),
+    let run = database::Run {
+        solver: solver.name(),
+        parameters: args.parameters.to_json(),
+        seed: args.seed,
+        iterations: args.iterations,
+        time_limit: args.time,
+        duration,
     };
+    // the stored solutions are the CodinGame ones, which `export` submits
+    if args.rule != ScoringRule::CodinGame {
+        eprintln!("Not stored: scored with the {} rule", args.rule);
+    } else {
+        match database::open()
+            .and_then(|conn| database::save_solution(&conn, &testcase, &run, &solution))
+        {
+            Ok(_) => eprintln!("Row inserted successfully!"),
+            Err(e) => eprintln!("Error: {:?}", e),
+        };
+    }
+
+    row
+}
+
+/// Solves a grid file of one of `GRID_SIZES`, one row of cells per line with
+/// the top row first, the colors being counted from the cells. Nothing is
+/// stored since the grid has no testcase hash.
+fn solve_grid(args: &Args, path: &str) -> io::Result<Solution> {
+    let grid = fs::read_to_string(path)?;
+    let size = input::grid_size(&grid).map_err(|e| io::Error::other(e.to_string()))?;
+    let colors = input::grid_colors(&grid)
+        .map_err(|e| io::Error::other(e.to_string()))?
+        .max(MIN_GRID_COLORS);
+    if colors > MAX_GRID_COLORS {
+        return Err(io::Error::other(format!(
+            "{} colors, at most {} are supported",
+            colors, MAX_GRID_COLORS
+        )));
+    }
+
+    match size {
+        (15, 15) => solve_grid_of_size::<15, 15>(args, &grid, colors),
+        // the classic SameGame benchmark boards
+        (20, 10) => solve_grid_of_size::<20, 10>(args, &grid, colors),
+        (width, height) => Err(io::Error::other(format!(
+            "unsupported grid size {}x{}, expected {}",
+            width,
+            height,
+            grid_sizes()
+        ))),
+    }
+}
+
+fn solve_grid_of_size<const W: usize, const H: usize>(
+    args: &Args,
+    grid: &str,
+    colors: usize,
+) -> io::Result<Solution> {
+    match colors {
+        2 => solve_grid_with::<W, H, 2>(args, grid),
+        3 => solve_grid_with::<W, H, 3>(args, grid),
+        4 => solve_grid_with::<W, H, 4>(args, grid),
+        5 => solve_grid_with::<W, H, 5>(args, grid),
+        6 => solve_grid_with::<W, H, 6>(args, grid),
+        _ => unreachable!("color count checked by solve_grid"),
+    }
+}
+
+fn solve_grid_with<const W: usize, const H: usize, const C: usize>(
+    args: &Args,
+    grid: &str,
+) -> io::Result<Solution> {
+    let budget = get_budget(args);
+    let cells = input::parse_grid::<W, H>(grid, C).map_err(|e| io::Error::other(e.to_string()))?;
+    let board: Board<W, H, C> = Board::with_rule(cells, args.rule);
+    eprintln!("{:?}", board);
+
+    let mut solver = get_solver(args);
+    eprintln!(
+        "{} with seed {} and the {} rule",
+        solver.name(),
+        args.seed,
+        args.rule
+    );
+    let solution = solver.solve(&board, &budget);
+    eprintln!("Score: {}, bound {}", solution.score, board.upper_bound());
+
+    Ok(solution)
+}
+
+fn run_batch(args: &Args, pattern: &str) {
+    let paths = match batch::find_testcases(pattern) {
+        Ok(paths) => paths,
+        Err(e) => {
+            eprintln!("Error: {:?}", e);
+            process::exit(1);
+        }
+    };
+    eprintln!("{} testcase(s) with {} job(s)", paths.len(), args.jobs);
+
+    let rows = batch::run(&paths, args.jobs, |path| solve_testcase(args, path));
+    batch::print_summary(&rows);
+}
+
+/// Prints the best verified solution of every selected testcase.
+fn export_solutions(args: &Args, pattern: &str) -> io::Result<()> {
+    let paths = batch::find_testcases(pattern)?;
+    let conn = database::open().map_err(io::Error::other)?;
+
+    let mut entries = Vec::new();
+    for path in paths {
+        let testcase = input::load_json(path.to_str().unwrap());
+        if !args.filter.accepts(&testcase) {
+            continue;
+        }
+
+        let solutions = database::load_solutions(&conn, testcase.hash).map_err(io::Error::other)?;
+        match export::best_verified(&testcase, solutions) {
+            Some(entry) => entries.push(entry),
+            None => eprintln!("{}: no verified solution", testcase.title),
+        }
+    }
+
+    // a recolored copy has the hash of its testset, only the best one is kept
+    let entries = export::best_per_hash(entries);
+    match args.format {
+        export::Format::Json => println!("{}", export::to_json(&entries)),
+        export::Format::Rust => print!("{}", export::to_rust(&entries)),
+    }
+    // every test has a validator of the same kind, as in `batch::total`
+    let total: i64 = entries.iter().map(|entry| entry.score as i64).sum();
+    eprintln!("{} solution(s), total score: {}", entries.len(), 2 * total);
+
+    Ok(())
+}
+
+/// Replays `actions`, or every solution stored for the testcase when none are
+/// given, and checks the recorded scores.
+fn verify_testcase(path: &str, actions: Option<&str>) -> bool {
+    let testcase = input::load_json(path);
+    let board: Board = Board::new(testcase.board);
+
+    let solutions = match actions {
+        Some(actions) => vec![(None, actions.to_string(), None)],
+        None => {
+            match database::open().and_then(|conn| database::load_solutions(&conn, testcase.hash)) {
+                Ok(rows) => rows
+                    .into_iter()
+                    .map(|row| (Some(row.id), row.actions, Some(row.score)))
+                    .collect(),
+                Err(e) => {
+                    eprintln!("Error: {:?}", e);
+                    return false;
+                }
+            }
+        }
+    };
+    eprintln!(
+        "{}: {} solution(s) to verify",
+        testcase.title,
+        solutions.len()
+    );
+
+    let mut valid = true;
+    for (id, actions, recorded) in solutions {
+        let name = id.map_or("Solution".to_string(), |id| format!("Row {}", id));
+        match verify::replay(&board, &actions) {
+            Ok(replayed) => {
+                let score = replayed.get_score();
+                match recorded {
+                    Some(recorded) if recorded != score => {
+                        valid = false;
+                        println!("{}: score {}, recorded {}", name, score, recorded)
+                    }
+                    _ => println!("{}: score {}", name, score),
+                }
+                match replayed.final_score() {
+                    Some(breakdown) => println!("{}: {}", name, breakdown),
+                    None => println!("{}: moves are left at the end", name),
+                }
+            }
+            Err(e) => {
+                valid = false;
+                println!("{}: {}", name, e);
+            }
+        }
+    }
+
+    valid
+}
+
+fn play_codingame(args: &Args) {
+    eprintln!("Seed: {}", args.seed);
+    let mut player = codingame::Player::new(
+        get_solver(args),
+        args.iterations,
+        args.time.unwrap_or(codingame::FIRST_TURN_SECS),
+        args.move_time.unwrap_or(codingame::MOVE_MILLIS),
+    );
+
+    if let Err(e) = codingame::run(&mut player, io::stdin().lock(), io::stdout()) {
+        eprintln!("Error: {:?}", e);
+    }
+}
+
+fn main() {
+    let args: Vec<String> = env::args().collect();
+    let args = parse_args(&args);
+
+    match args.mode {
+        Mode::Solve(ref path) if path.ends_with(".json") => {
+            let row = solve_testcase(&args, Path::new(path));
+            println!("{}", row.actions);
+        }
+        Mode::Solve(ref path) => match solve_grid(&args, path) {
+            Ok(solution) => println!("{}", solution.actions),
+            Err(e) => {
+                eprintln!("Error: {:?}", e);
+                process::exit(1);
+            }
+        },
+        Mode::Batch(ref pattern) => run_batch(&args, pattern),
+        Mode::Export(ref pattern) => {
+            if let Err(e) = export_solutions(&args, pattern) {
+                eprintln!("Error: {:?}", e);
+                process::exit(1);
+            }
+        }
+        Mode::CodinGame => play_codingame(&args),
+        Mode::Verify(ref path, ref actions) => {
+            if !verify_testcase(path, actions.as_deref()) {
+                process::exit(1);
+            }
+        }
+    }
 }
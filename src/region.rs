@@ -9,11 +9,12 @@ impl Region {
         self.positions.len()
     }
 
-    pub fn score(&self) -> u32 {
-        if self.positions.len() < 2 {
-            return 0;
-        }
-
-        u32::pow((self.positions.len() - 2) as u32, 2)
+    /// Whether both regions cover the same cells, in whatever order.
+    pub fn same_cells(&self, other: &Region) -> bool {
+        let mut mine = self.positions.clone();
+        let mut theirs = other.positions.clone();
+        mine.sort_unstable();
+        theirs.sort_unstable();
+        mine == theirs
     }
 }
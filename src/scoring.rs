@@ -0,0 +1,155 @@
+use std::{fmt, str::FromStr};
+
+pub const CLEAR_BONUS: i32 = 1000;
+
+/// How a game is scored, which is the only difference between the SameGame
+/// variants; every solver maximizes the score of the rule of its board.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum ScoringRule {
+    /// `(n - 2)^2` for a region of `n` cells, plus 1000 for clearing the board.
+    #[default]
+    CodinGame,
+    /// SameGame of JS-Games, used by the academic benchmark: the CodinGame
+    /// points, minus `(n - 2)^2` for each color with `n` cells left at the end.
+    JsGames,
+    /// Clickomania: one point per removed cell, so that the best game is the
+    /// one leaving the fewest cells.
+    Clickomania,
+}
+
+impl ScoringRule {
+    pub const NAMES: [&'static str; 3] = ["codingame", "jsgames", "clickomania"];
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            ScoringRule::CodinGame => "codingame",
+            ScoringRule::JsGames => "jsgames",
+            ScoringRule::Clickomania => "clickomania",
+        }
+    }
+
+    /// Points for removing a region of `cells` cells.
+    pub fn region_points(&self, cells: usize) -> i32 {
+        match self {
+            ScoringRule::CodinGame | ScoringRule::JsGames => (cells as i32 - 2).pow(2),
+            ScoringRule::Clickomania => cells as i32,
+        }
+    }
+
+    /// Points for removing the last cell of the board.
+    pub fn clear_bonus(&self) -> i32 {
+        match self {
+            ScoringRule::CodinGame | ScoringRule::JsGames => CLEAR_BONUS,
+            ScoringRule::Clickomania => 0,
+        }
+    }
+
+    /// Most points that removing the cells counted in `color_counts` can give:
+    /// each color removed as one region, which no split beats, plus the clear
+    /// bonus unless a color has a single cell, which can never be removed.
+    pub fn upper_bound(&self, color_counts: &[u16]) -> i32 {
+        let regions: i32 = color_counts
+            .iter()
+            .filter(|&&count| count >= 2)
+            .map(|&count| self.region_points(count as usize))
+            .sum();
+        let clearable = color_counts.iter().all(|&count| count != 1);
+
+        regions + if clearable { self.clear_bonus() } else { 0 }
+    }
+
+    /// Whether `end_of_game` can be non zero, so that boards only look for
+    /// the end of the game when it matters.
+    pub fn scores_leftovers(&self) -> bool {
+        *self == ScoringRule::JsGames
+    }
+
+    /// End-of-game hook: points added once no move is left, from the number
+    /// of cells left of each color.
+    pub fn end_of_game(&self, color_counts: &[u16]) -> i32 {
+        match self {
+            ScoringRule::JsGames => -color_counts
+                .iter()
+                .filter(|&&count| count > 0)
+                .map(|&count| (count as i32 - 2).pow(2))
+                .sum::<i32>(),
+            ScoringRule::CodinGame | ScoringRule::Clickomania => 0,
+        }
+    }
+}
+
+/// Final score of a game split by where the points come from.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ScoreBreakdown {
+    pub region_points: i32,
+    pub clear_bonus: i32,
+    /// Zero or negative, from `ScoringRule::end_of_game`.
+    pub leftover_penalty: i32,
+}
+
+impl ScoreBreakdown {
+    pub fn total(&self) -> i32 {
+        self.region_points + self.clear_bonus + self.leftover_penalty
+    }
+}
+
+impl fmt::Display for ScoreBreakdown {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{} (regions {}, clear bonus {}, leftovers {})",
+            self.total(),
+            self.region_points,
+            self.clear_bonus,
+            self.leftover_penalty
+        )
+    }
+}
+
+impl fmt::Display for ScoringRule {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.name())
+    }
+}
+
+impl FromStr for ScoringRule {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<ScoringRule, String> {
+        match s {
+            "codingame" => Ok(ScoringRule::CodinGame),
+            "jsgames" => Ok(ScoringRule::JsGames),
+            "clickomania" => Ok(ScoringRule::Clickomania),
+            _ => Err(format!(
+                "Unknown scoring rule {}, expected one of {}",
+                s,
+                ScoringRule::NAMES.join(", ")
+            )),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_rules() {
+        assert_eq!(ScoringRule::CodinGame.region_points(5), 9);
+        assert_eq!(ScoringRule::JsGames.region_points(2), 0);
+        assert_eq!(ScoringRule::Clickomania.region_points(5), 5);
+
+        assert_eq!(ScoringRule::CodinGame.end_of_game(&[3, 0, 1]), 0);
+        assert_eq!(ScoringRule::JsGames.end_of_game(&[3, 0, 1, 7]), -27);
+        assert_eq!(ScoringRule::Clickomania.clear_bonus(), 0);
+
+        assert_eq!(ScoringRule::CodinGame.upper_bound(&[5, 0, 2]), 1009);
+        assert_eq!(ScoringRule::JsGames.upper_bound(&[5, 1, 2]), 9);
+        assert_eq!(ScoringRule::Clickomania.upper_bound(&[5, 1, 2]), 7);
+
+        for name in ScoringRule::NAMES {
+            assert_eq!(name.parse::<ScoringRule>().unwrap().name(), name);
+        }
+        assert!("samegame".parse::<ScoringRule>().is_err());
+    }
+}
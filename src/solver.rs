@@ -1,6 +1,277 @@
+use rand::{rngs::SmallRng, SeedableRng};
+use std::{
+    collections::{BTreeMap, HashMap},
+    fmt::Debug,
+    str::FromStr,
+    time::{Duration, Instant},
+};
+
 use crate::board::Board;
-use crate::solvers::tabucolor::_solve;
+use crate::endgame::WithEndgame;
+use crate::solvers::{
+    beam::Beam, hgsts::Hgsts, iterbeam::IterBeam, mcrws::Mcrws, nmcs::Nmcs, nrpa::Nrpa,
+    tabucolor::TabuColor,
+};
+
+pub const SOLVERS: [&str; 7] = [
+    "beam",
+    "hgsts",
+    "iterbeam",
+    "mcrws",
+    "nmcs",
+    "nrpa",
+    "tabucolor",
+];
+pub const DEFAULT_SOLVER: &str = "tabucolor";
+pub const DEFAULT_SEED: u64 = 0;
+
+/// `key=value` settings given on the command line, read by each solver in
+/// `Solver::configure`.
+#[derive(Debug, Default)]
+pub struct Parameters {
+    values: HashMap<String, String>,
+}
+
+impl Parameters {
+    pub fn insert(&mut self, key: &str, value: &str) {
+        self.values.insert(key.to_string(), value.to_string());
+    }
+
+    pub fn parse_pair(&mut self, pair: &str) -> bool {
+        match pair.split_once('=') {
+            Some((key, value)) => {
+                self.insert(key, value);
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// The pairs as a JSON object with sorted keys, to record a run.
+    pub fn to_json(&self) -> String {
+        let sorted: BTreeMap<&String, &String> = self.values.iter().collect();
+        serde_json::to_string(&sorted).unwrap()
+    }
+
+    pub fn get<T: FromStr>(&self, key: &str, default: T) -> T
+    where
+        T::Err: Debug,
+    {
+        self.get_opt(key).unwrap_or(default)
+    }
+
+    pub fn get_opt<T: FromStr>(&self, key: &str) -> Option<T>
+    where
+        T::Err: Debug,
+    {
+        self.values.get(key).map(|value| {
+            value
+                .parse()
+                .unwrap_or_else(|e| panic!("Invalid value {:?} for {}: {:?}", value, key, e))
+        })
+    }
+}
+
+/// How much work a solver may spend: an optional cap on the solver's main
+/// loop count and an optional wall-clock deadline. What the cap counts is up
+/// to each solver, see the documentation of the solver structs. Sub-budgets
+/// created with `split` never outlive the deadline of the budget they come
+/// from.
+#[derive(Debug, Default, Clone, Copy)]
+pub struct Budget {
+    pub iterations: Option<usize>,
+    deadline: Option<Instant>,
+}
+
+impl Budget {
+    pub fn from_duration(duration: Duration) -> Budget {
+        Budget {
+            iterations: None,
+            deadline: Some(Instant::now() + duration),
+        }
+    }
+
+    pub fn from_secs(secs: f64) -> Budget {
+        Budget::from_duration(Duration::from_secs_f64(secs))
+    }
+
+    pub fn from_millis(millis: u64) -> Budget {
+        Budget::from_duration(Duration::from_millis(millis))
+    }
+
+    pub fn with_iterations(mut self, iterations: Option<usize>) -> Budget {
+        self.iterations = iterations;
+        self
+    }
+
+    /// Loop count to run: the explicit cap, unbounded when only a deadline is
+    /// set (the deadline stops the loop), the solver default otherwise.
+    pub fn iterations_or(&self, default: usize) -> usize {
+        match (self.iterations, self.deadline) {
+            (Some(iterations), _) => iterations,
+            (None, Some(_)) => usize::MAX,
+            (None, None) => default,
+        }
+    }
+
+    pub fn is_expired(&self) -> bool {
+        match self.deadline {
+            Some(deadline) => Instant::now() >= deadline,
+            None => false,
+        }
+    }
+
+    pub fn remaining(&self) -> Option<Duration> {
+        self.deadline
+            .map(|deadline| deadline.saturating_duration_since(Instant::now()))
+    }
+
+    /// Budget for one of `parts` consecutive steps sharing what is left.
+    pub fn split(&self, parts: usize) -> Budget {
+        let deadline = self
+            .remaining()
+            .map(|remaining| Instant::now() + remaining / parts.max(1) as u32);
+
+        Budget {
+            iterations: self.iterations,
+            deadline,
+        }
+    }
+}
+
+/// Random number generator of the solvers. Every generator is created from
+/// the `seed` parameter, so that a recorded run can be replayed exactly.
+pub type SolverRng = SmallRng;
+
+/// Generator for the `index`-th thread (or restart) of a run seeded with `seed`.
+pub fn seeded_rng(seed: u64, index: usize) -> SolverRng {
+    SmallRng::seed_from_u64(seed ^ (index as u64).wrapping_mul(0x9E37_79B9_7F4A_7C15))
+}
+
+#[derive(Debug)]
+pub struct Solution {
+    pub actions: String,
+    pub score: i32,
+}
+
+impl Solution {
+    pub fn from_board<const W: usize, const H: usize, const C: usize>(
+        board: &Board<W, H, C>,
+    ) -> Solution {
+        Solution {
+            actions: board.get_actions_str(),
+            score: board.get_score(),
+        }
+    }
+}
+
+/// A search for one board size, the CodinGame one by default.
+pub trait Solver<const W: usize = 15, const H: usize = 15, const C: usize = 5> {
+    fn name(&self) -> &'static str;
+    fn configure(&mut self, parameters: &Parameters);
+    fn solve(&mut self, initial_state: &Board<W, H, C>, budget: &Budget) -> Solution;
+}
+
+pub fn get_solver<const W: usize, const H: usize, const C: usize>(
+    name: &str,
+) -> Option<Box<dyn Solver<W, H, C>>> {
+    let solver: Box<dyn Solver<W, H, C>> = match name {
+        "beam" => Box::new(Beam::default()),
+        "hgsts" => Box::new(Hgsts::default()),
+        "iterbeam" => Box::new(IterBeam::default()),
+        "mcrws" => Box::new(Mcrws::default()),
+        "nmcs" => Box::new(Nmcs::default()),
+        "nrpa" => Box::new(Nrpa::default()),
+        "tabucolor" => Box::new(TabuColor::default()),
+        _ => return None,
+    };
+    // every solver finishes its game with the exact endgame search
+    Some(Box::new(WithEndgame::new(solver)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_util;
+
+    #[test]
+    fn test_registry() {
+        for name in SOLVERS {
+            assert_eq!(get_solver::<15, 15, 5>(name).unwrap().name(), name);
+        }
+        assert!(get_solver::<15, 15, 5>("unknown").is_none());
+    }
+
+    #[test]
+    fn test_seed_reproducible() {
+        let board = test_util::mixed_board();
+
+        let mut parameters = Parameters::default();
+        for pair in ["seed=7", "level=1", "n=10"] {
+            parameters.parse_pair(pair);
+        }
+        let budget = Budget::default().with_iterations(Some(3));
+
+        for name in SOLVERS {
+            let solve = || {
+                let mut solver = get_solver(name).unwrap();
+                solver.configure(&parameters);
+                solver.solve(&board, &budget)
+            };
+            assert_eq!(solve().actions, solve().actions, "{}", name);
+        }
+    }
+
+    #[test]
+    fn test_other_size() {
+        let board: Board<20, 10, 3> =
+            Board::new(test_util::pattern(|x, y| (x * 7 + y * 3 + x * y) % 3));
+
+        let mut parameters = Parameters::default();
+        for pair in ["level=1", "n=10"] {
+            parameters.parse_pair(pair);
+        }
+        let budget = Budget::default().with_iterations(Some(3));
+
+        for name in SOLVERS {
+            let mut solver = get_solver(name).unwrap();
+            solver.configure(&parameters);
+            let solution = solver.solve(&board, &budget);
+
+            let replayed = crate::verify::replay(&board, &solution.actions).unwrap();
+            assert_eq!(replayed.get_score(), solution.score, "{}", name);
+            assert!(replayed.compute_all_regions().is_empty(), "{}", name);
+        }
+    }
+
+    #[test]
+    fn test_parameters() {
+        let mut parameters = Parameters::default();
+
+        assert!(parameters.parse_pair("k=12"));
+        assert!(!parameters.parse_pair("k"));
+
+        assert_eq!(parameters.get("k", 3000), 12);
+        assert_eq!(parameters.get("c", 0.5), 0.5);
+
+        parameters.insert("c", "0.7");
+        assert_eq!(parameters.to_json(), r#"{"c":"0.7","k":"12"}"#);
+    }
+
+    #[test]
+    fn test_budget() {
+        assert_eq!(Budget::default().iterations_or(3000), 3000);
+        assert!(!Budget::default().is_expired());
+
+        let budget = Budget::from_secs(10.0);
+        assert_eq!(budget.iterations_or(3000), usize::MAX);
+        assert_eq!(budget.with_iterations(Some(5)).iterations_or(3000), 5);
+
+        let part = budget.split(4);
+        assert!(part.remaining().unwrap() <= Duration::from_millis(2500));
+        assert!(part.remaining().unwrap() > Duration::from_millis(2000));
 
-pub fn solve(initial_state: &Board) -> (String, u32) {
-    _solve(initial_state)
+        assert!(Budget::from_duration(Duration::ZERO).is_expired());
+        assert!(Budget::from_duration(Duration::ZERO).split(3).is_expired());
+    }
 }
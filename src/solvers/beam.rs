@@ -0,0 +1,229 @@
+use fxhash::FxHashMap;
+
+use crate::{
+    board::Board,
+    endgame::{Endgame, ENDGAME_CELLS},
+    solver::{Budget, Parameters, Solution, Solver},
+};
+
+const WIDTH: usize = 100;
+const WEIGHT: f64 = 0.5;
+
+/// How the boards of one depth are ranked to keep the best `width` of them.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Evaluation {
+    /// Points scored so far.
+    Score,
+    /// Points scored plus `weight` times the points each color would give if
+    /// all its cells were removed as one group, under the rule of the board.
+    ColorPotential(f64),
+    /// Points scored minus `weight` per cell still on the board.
+    RemainingPenalty(f64),
+}
+
+impl Evaluation {
+    pub fn from_name(name: &str, weight: f64) -> Option<Evaluation> {
+        match name {
+            "score" => Some(Evaluation::Score),
+            "potential" => Some(Evaluation::ColorPotential(weight)),
+            "remaining" => Some(Evaluation::RemainingPenalty(weight)),
+            _ => None,
+        }
+    }
+
+    pub fn evaluate<const W: usize, const H: usize, const C: usize>(
+        &self,
+        board: &Board<W, H, C>,
+    ) -> f64 {
+        let score = board.get_score() as f64;
+        match self {
+            Evaluation::Score => score,
+            Evaluation::ColorPotential(weight) => {
+                let rule = board.rule();
+                let potential: i32 = board
+                    .get_color_counts()
+                    .iter()
+                    .filter(|&&count| count >= 2)
+                    .map(|&count| rule.region_points(count as usize))
+                    .sum();
+                score + weight * potential as f64
+            }
+            Evaluation::RemainingPenalty(weight) => score - weight * board.remaining_cells() as f64,
+        }
+    }
+}
+
+/// `--iterations` sets the beam width, the `width` parameter.
+pub struct Beam {
+    width: usize,
+    evaluation: Evaluation,
+    endgame: usize,
+}
+
+impl Default for Beam {
+    fn default() -> Beam {
+        Beam {
+            width: WIDTH,
+            evaluation: Evaluation::ColorPotential(WEIGHT),
+            endgame: ENDGAME_CELLS,
+        }
+    }
+}
+
+impl<const W: usize, const H: usize, const C: usize> Solver<W, H, C> for Beam {
+    fn name(&self) -> &'static str {
+        "beam"
+    }
+
+    fn configure(&mut self, parameters: &Parameters) {
+        self.width = parameters.get("width", self.width);
+        let name: String = parameters.get("eval", "potential".to_string());
+        let weight = parameters.get("weight", WEIGHT);
+        self.evaluation = Evaluation::from_name(&name, weight)
+            .unwrap_or_else(|| panic!("Unknown evaluation {}", name));
+        self.endgame = parameters.get("endgame", self.endgame);
+    }
+
+    fn solve(&mut self, initial_state: &Board<W, H, C>, budget: &Budget) -> Solution {
+        let width = budget.iterations.unwrap_or(self.width);
+        let mut endgame = Endgame::new(self.endgame);
+        let best = beam_search(initial_state, width, self.evaluation, &mut endgame, budget);
+
+        Solution::from_board(&best)
+    }
+}
+
+/// Deterministic beam search, returns the best terminal board reached. Boards
+/// reached by several move orders are kept once, with their best score, and
+/// the boards where `endgame` applies are finished by it instead of being
+/// expanded. When the budget expires the beam shrinks to its best board,
+/// finished greedily.
+pub(crate) fn beam_search<const W: usize, const H: usize, const C: usize>(
+    initial_state: &Board<W, H, C>,
+    width: usize,
+    evaluation: Evaluation,
+    endgame: &mut Endgame,
+    budget: &Budget,
+) -> Board<W, H, C> {
+    let mut beam = vec![initial_state.clone()];
+    let mut best: Option<Board<W, H, C>> = None;
+
+    while !beam.is_empty() {
+        let mut candidates: Vec<(f64, Board<W, H, C>)> = Vec::new();
+        let mut seen: FxHashMap<u64, usize> = FxHashMap::default();
+
+        for board in beam.iter() {
+            let finish = match endgame.solve(board, budget) {
+                Some(finish) => Some(finish),
+                None if board.is_terminal() => Some(board.clone()),
+                None => None,
+            };
+            if let Some(finish) = finish {
+                if best
+                    .as_ref()
+                    .is_none_or(|best| finish.get_score() > best.get_score())
+                {
+                    best = Some(finish);
+                }
+                continue;
+            }
+
+            let all_regions = board.compute_all_regions();
+
+            for region in all_regions.iter() {
+                let mut child = board.clone();
+                child.play_region(region);
+                let value = evaluation.evaluate(&child);
+
+                match seen.get(&child.zobrist()) {
+                    Some(&i) => {
+                        if child.get_score() > candidates[i].1.get_score() {
+                            candidates[i] = (value, child);
+                        }
+                    }
+                    None => {
+                        seen.insert(child.zobrist(), candidates.len());
+                        candidates.push((value, child));
+                    }
+                }
+            }
+        }
+
+        candidates.sort_by(|a, b| b.0.total_cmp(&a.0));
+        let width = if budget.is_expired() { 1 } else { width };
+        beam = candidates
+            .into_iter()
+            .take(width)
+            .map(|(_, board)| board)
+            .collect();
+    }
+
+    best.unwrap_or_else(|| initial_state.clone())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_util::mixed_board;
+
+    #[test]
+    fn test_evaluation() {
+        let board = mixed_board();
+        let counts = board.get_color_counts();
+        let remaining: u32 = counts.iter().map(|&c| c as u32).sum();
+
+        assert_eq!(Evaluation::Score.evaluate(&board), 0.0);
+        assert_eq!(
+            Evaluation::RemainingPenalty(2.0).evaluate(&board),
+            -2.0 * remaining as f64
+        );
+        assert!(Evaluation::ColorPotential(1.0).evaluate(&board) > 0.0);
+        assert_eq!(Evaluation::from_name("unknown", 1.0), None);
+    }
+
+    #[test]
+    fn test_deterministic() {
+        let board = mixed_board();
+        let evaluation = Evaluation::ColorPotential(WEIGHT);
+
+        let first = beam_search(
+            &board,
+            10,
+            evaluation,
+            &mut Endgame::default(),
+            &Budget::default(),
+        );
+        let second = beam_search(
+            &board,
+            10,
+            evaluation,
+            &mut Endgame::default(),
+            &Budget::default(),
+        );
+
+        assert!(first.compute_all_regions().is_empty());
+        assert_eq!(first.get_actions_str(), second.get_actions_str());
+    }
+
+    #[test]
+    fn test_wider_is_not_worse_than_greedy() {
+        let board = mixed_board();
+
+        let greedy = beam_search(
+            &board,
+            1,
+            Evaluation::Score,
+            &mut Endgame::default(),
+            &Budget::default(),
+        );
+        let wide = beam_search(
+            &board,
+            20,
+            Evaluation::Score,
+            &mut Endgame::default(),
+            &Budget::default(),
+        );
+
+        assert!(wide.get_score() >= greedy.get_score());
+    }
+}
@@ -1 +1,133 @@
 // https://dke.maastrichtuniversity.nl/m.winands/documents/KNOSYS_SameGame.pdf
+
+use lru::LruCache;
+use std::num::NonZeroUsize;
+
+use crate::{
+    board::Board,
+    endgame::{Endgame, ENDGAME_CELLS},
+    region::Region,
+    solver::{seeded_rng, Budget, Parameters, Solution, Solver, DEFAULT_SEED},
+    solvers::mcts::{self, Settings, Uct},
+};
+
+// tuned to the CodinGame points, `Uct::scaled_to` adapts them to the rule
+const UCT_CONSTANT: f64 = 0.5;
+const DEVIATION_CONSTANT: f64 = 10000.0;
+const TOP_SCORE_WEIGHT: f64 = 0.1;
+const VISITS_BEFORE_EXPAND: f64 = 10.0;
+const ITERATIONS_PER_RESTART: usize = 100000;
+const RESTARTS: usize = 10;
+
+/// SP-MCTS: the tree search of `mcts` with random rollouts that only avoid
+/// the most frequent color, restarted with a fresh tree to keep the best game.
+/// `--iterations` sets the iterations of each restart, the `iterations`
+/// parameter.
+pub struct Hgsts {
+    settings: Settings,
+    iterations_per_restart: usize,
+    restarts: usize,
+    seed: u64,
+    endgame: usize,
+}
+
+impl Default for Hgsts {
+    fn default() -> Hgsts {
+        Hgsts {
+            settings: Settings {
+                uct: Uct {
+                    exploration: UCT_CONSTANT,
+                    deviation: DEVIATION_CONSTANT,
+                    top_score_weight: TOP_SCORE_WEIGHT,
+                },
+                visits_before_expand: VISITS_BEFORE_EXPAND,
+                chance_tabu_color: 0.0,
+                prune: false,
+                bitboard: false,
+            },
+            iterations_per_restart: ITERATIONS_PER_RESTART,
+            restarts: RESTARTS,
+            seed: DEFAULT_SEED,
+            endgame: ENDGAME_CELLS,
+        }
+    }
+}
+
+impl<const W: usize, const H: usize, const C: usize> Solver<W, H, C> for Hgsts {
+    fn name(&self) -> &'static str {
+        "hgsts"
+    }
+
+    fn configure(&mut self, parameters: &Parameters) {
+        let settings = &mut self.settings;
+        settings.uct.exploration = parameters.get("c", settings.uct.exploration);
+        settings.uct.deviation = parameters.get("d", settings.uct.deviation);
+        settings.uct.top_score_weight = parameters.get("top_weight", settings.uct.top_score_weight);
+        settings.visits_before_expand = parameters.get("expand", settings.visits_before_expand);
+        settings.prune = parameters.get("prune", settings.prune);
+        settings.bitboard = parameters.get("bitboard", settings.bitboard);
+        self.iterations_per_restart = parameters.get("iterations", self.iterations_per_restart);
+        self.restarts = parameters.get("restarts", self.restarts);
+        self.seed = parameters.get("seed", self.seed);
+        self.endgame = parameters.get("endgame", self.endgame);
+    }
+
+    fn solve(&mut self, initial_state: &Board<W, H, C>, budget: &Budget) -> Solution {
+        let mut best_probe: Option<Board<W, H, C>> = None;
+        let iterations = budget.iterations_or(self.iterations_per_restart);
+
+        let mut cache_region: LruCache<u64, Vec<Region>> =
+            LruCache::new(NonZeroUsize::new(1000000).unwrap());
+        let mut rng = seeded_rng(self.seed, 0);
+        let mut endgame = Endgame::new(self.endgame);
+
+        // meta-search: independent randomized restarts, keeping the best sequence
+        for restart in 0..self.restarts {
+            if restart > 0 && budget.is_expired() {
+                break;
+            }
+            let restart_budget = budget.split(self.restarts - restart);
+            let (probe, _) = mcts::search(
+                initial_state,
+                &self.settings,
+                iterations,
+                &restart_budget,
+                &mut cache_region,
+                &mut endgame,
+                &mut rng,
+            );
+
+            eprintln!("Restart {}: {}", restart, probe.get_score());
+            if best_probe
+                .as_ref()
+                .is_none_or(|best| probe.get_score() > best.get_score())
+            {
+                best_probe = Some(probe);
+            }
+        }
+
+        Solution::from_board(&best_probe.unwrap_or_else(|| initial_state.clone()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_util::blocks_board;
+
+    #[test]
+    fn test_restarts() {
+        let board = blocks_board();
+        let mut hgsts = Hgsts {
+            restarts: 3,
+            ..Hgsts::default()
+        };
+
+        let budget = Budget::default().with_iterations(Some(500));
+        let solution = Solver::<15, 15, 5>::solve(&mut hgsts, &board, &budget);
+
+        let replayed = crate::verify::replay(&board, &solution.actions).unwrap();
+        assert_eq!(replayed.get_score(), solution.score);
+        assert!(replayed.compute_all_regions().is_empty());
+    }
+}
@@ -0,0 +1,175 @@
+use crate::{
+    board::Board,
+    endgame::{Endgame, ENDGAME_CELLS},
+    solver::{Budget, Parameters, Solution, Solver},
+    solvers::beam::{beam_search, Evaluation},
+};
+
+const INITIAL_WIDTH: usize = 10;
+const WIDTH_GROWTH: usize = 2;
+const PASSES: usize = 4;
+const WEIGHT: f64 = 0.5;
+
+/// Iterated beam search: every pass runs a beam from each prefix of the best
+/// sequence found so far, the incumbent being replaced only by a better one,
+/// then the next pass uses a wider beam. `--iterations` sets the number of
+/// passes, the `passes` parameter; the search also stops once a pass leaves
+/// the incumbent unchanged or the width cannot grow any more.
+pub struct IterBeam {
+    initial_width: usize,
+    width_growth: usize,
+    passes: usize,
+    evaluation: Evaluation,
+    endgame: usize,
+}
+
+impl Default for IterBeam {
+    fn default() -> IterBeam {
+        IterBeam {
+            initial_width: INITIAL_WIDTH,
+            width_growth: WIDTH_GROWTH,
+            passes: PASSES,
+            evaluation: Evaluation::ColorPotential(WEIGHT),
+            endgame: ENDGAME_CELLS,
+        }
+    }
+}
+
+impl<const W: usize, const H: usize, const C: usize> Solver<W, H, C> for IterBeam {
+    fn name(&self) -> &'static str {
+        "iterbeam"
+    }
+
+    fn configure(&mut self, parameters: &Parameters) {
+        self.initial_width = parameters.get("width", self.initial_width);
+        self.width_growth = parameters.get("growth", self.width_growth);
+        self.passes = parameters.get("passes", self.passes);
+        let name: String = parameters.get("eval", "potential".to_string());
+        let weight = parameters.get("weight", WEIGHT);
+        self.evaluation = Evaluation::from_name(&name, weight)
+            .unwrap_or_else(|| panic!("Unknown evaluation {}", name));
+        self.endgame = parameters.get("endgame", self.endgame);
+        assert!(self.initial_width > 0, "The width must be positive");
+        assert!(self.width_growth >= 2, "The growth must be at least 2");
+    }
+
+    fn solve(&mut self, initial_state: &Board<W, H, C>, budget: &Budget) -> Solution {
+        let mut width = self.initial_width;
+        let mut endgame = Endgame::new(self.endgame);
+        let mut incumbent =
+            beam_search(initial_state, width, self.evaluation, &mut endgame, budget);
+        eprintln!("Width {}: {}", width, incumbent.get_score());
+
+        for _ in 1..budget.iterations_or(self.passes) {
+            if budget.is_expired() {
+                break;
+            }
+            let wider = width.saturating_mul(self.width_growth);
+            if wider == width {
+                break;
+            }
+            width = wider;
+
+            let previous = incumbent.get_actions().to_vec();
+            incumbent = self.refine(initial_state, incumbent, width, &mut endgame, budget);
+            eprintln!("Width {}: {}", width, incumbent.get_score());
+            if incumbent.get_actions() == previous {
+                break;
+            }
+        }
+
+        Solution::from_board(&incumbent)
+    }
+}
+
+impl IterBeam {
+    fn refine<const W: usize, const H: usize, const C: usize>(
+        &self,
+        initial_state: &Board<W, H, C>,
+        mut incumbent: Board<W, H, C>,
+        width: usize,
+        endgame: &mut Endgame,
+        budget: &Budget,
+    ) -> Board<W, H, C> {
+        let mut prefix = initial_state.clone();
+        let mut depth = 0;
+
+        while depth < incumbent.get_actions().len() && !budget.is_expired() {
+            let candidate = beam_search(&prefix, width, self.evaluation, endgame, budget);
+            if candidate.get_score() > incumbent.get_score() {
+                eprintln!(
+                    "Depth {}: {} -> {}",
+                    depth,
+                    incumbent.get_score(),
+                    candidate.get_score()
+                );
+                incumbent = candidate;
+            }
+
+            // the prefix follows the incumbent, which may just have changed
+            prefix.play_index(incumbent.get_actions()[depth]);
+            depth += 1;
+        }
+
+        incumbent
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_util::mixed_board;
+
+    #[test]
+    fn test_keeps_incumbent() {
+        let board = mixed_board();
+        let solver = IterBeam::default();
+        let mut endgame = Endgame::default();
+        let budget = Budget::default();
+        let incumbent = beam_search(&board, 2, Evaluation::Score, &mut endgame, &budget);
+
+        let refined = solver.refine(&board, incumbent.clone(), 2, &mut endgame, &budget);
+
+        assert!(refined.get_score() >= incumbent.get_score());
+        assert!(refined.compute_all_regions().is_empty());
+    }
+
+    #[test]
+    fn test_solve() {
+        let board = mixed_board();
+        let mut solver = IterBeam::default();
+        let mut endgame = Endgame::default();
+        let budget = Budget::default();
+        let single = beam_search(
+            &board,
+            INITIAL_WIDTH,
+            solver.evaluation,
+            &mut endgame,
+            &budget,
+        );
+
+        let solution = solver.solve(&board, &Budget::default().with_iterations(Some(2)));
+
+        assert!(solution.score >= single.get_score());
+    }
+
+    #[test]
+    fn test_time_only_budget() {
+        // one color: every pass leaves the single move unchanged, whatever the
+        // number of passes the deadline allows
+        let board: Board = Board::new([[0; 15]; 15]);
+        let mut solver = IterBeam::default();
+
+        let solution = Solver::<15, 15, 5>::solve(&mut solver, &board, &Budget::from_secs(60.0));
+
+        assert_eq!(solution.actions.split_whitespace().count(), 2);
+    }
+
+    #[test]
+    #[should_panic(expected = "The growth must be at least 2")]
+    fn test_configure_growth() {
+        let mut parameters = Parameters::default();
+        parameters.insert("growth", "1");
+        Solver::<15, 15, 5>::configure(&mut IterBeam::default(), &parameters);
+    }
+}
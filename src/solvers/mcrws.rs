@@ -2,17 +2,145 @@
 
 use lru::LruCache;
 use rand::Rng;
-use std::num::NonZeroUsize;
+use std::{num::NonZeroUsize, thread};
+
+use crate::{
+    board::Board,
+    endgame::{Endgame, ENDGAME_CELLS},
+    region::Region,
+    solver::{seeded_rng, Budget, Parameters, Solution, Solver, SolverRng, DEFAULT_SEED},
+};
+
+const ROLLOUTS_PER_CHILD: usize = 3000;
+const THREADS: usize = 1;
+const CACHE_SIZE: usize = 1000000;
+/// Cells removed by an average move, about 50 moves on a full 15x15 board.
+const CELLS_PER_MOVE: usize = 4;
+
+/// `--iterations` sets the rollouts run for each child of every position,
+/// the `k` parameter.
+pub struct Mcrws {
+    rollouts_per_child: usize,
+    threads: usize,
+    seed: u64,
+    endgame: usize,
+}
+
+impl Default for Mcrws {
+    fn default() -> Mcrws {
+        Mcrws {
+            rollouts_per_child: ROLLOUTS_PER_CHILD,
+            threads: THREADS,
+            seed: DEFAULT_SEED,
+            endgame: ENDGAME_CELLS,
+        }
+    }
+}
+
+impl<const W: usize, const H: usize, const C: usize> Solver<W, H, C> for Mcrws {
+    fn name(&self) -> &'static str {
+        "mcrws"
+    }
+
+    fn configure(&mut self, parameters: &Parameters) {
+        self.rollouts_per_child = parameters.get("k", self.rollouts_per_child);
+        self.threads = parameters.get("threads", self.threads).max(1);
+        self.seed = parameters.get("seed", self.seed);
+        self.endgame = parameters.get("endgame", self.endgame);
+    }
+
+    fn solve(&mut self, initial_state: &Board<W, H, C>, budget: &Budget) -> Solution {
+        eprintln!("Threads: {}", self.threads);
+
+        _solve(
+            initial_state,
+            budget.iterations_or(self.rollouts_per_child),
+            self.threads,
+            self.seed,
+            self.endgame,
+            budget,
+        )
+    }
+}
+
+/// State owned by one thread for the whole search, so that a run only depends
+/// on the seed and on the number of threads.
+struct Worker {
+    rng: SolverRng,
+    cache: LruCache<u64, Vec<Region>>,
+    endgame: Endgame,
+}
+
+/// Sum of the scores and number of rollouts for each child of a position.
+type ChildScores = Vec<(i64, u64)>;
 
-use crate::{board::Board, region::Region};
+impl Worker {
+    fn new(seed: u64, index: usize, cache_size: usize, endgame: usize) -> Worker {
+        Worker {
+            rng: seeded_rng(seed, index),
+            cache: LruCache::new(NonZeroUsize::new(cache_size.max(1)).unwrap()),
+            endgame: Endgame::new(endgame),
+        }
+    }
+
+    /// Runs this worker's share of the rollouts of every child of `board`.
+    fn evaluate<const W: usize, const H: usize, const C: usize>(
+        &mut self,
+        board: &Board<W, H, C>,
+        all_regions: &[Region],
+        rollouts: usize,
+        depth_budget: &Budget,
+    ) -> (ChildScores, Option<Board<W, H, C>>) {
+        let mut board = board.clone();
+        let mut best_probe: Option<Board<W, H, C>> = None;
+        let mut scores = Vec::with_capacity(all_regions.len());
+
+        for (i, region) in all_regions.iter().enumerate() {
+            let child_budget = depth_budget.split(all_regions.len() - i);
+            let undo = board.play_region_with_undo(region);
+
+            let mut total_score = 0i64;
+            let mut probes = 0;
+            while probes < rollouts && (probes == 0 || !child_budget.is_expired()) {
+                let probe = rollout(
+                    &board,
+                    &mut self.cache,
+                    &mut self.endgame,
+                    &child_budget,
+                    &mut self.rng,
+                );
+                total_score += probe.get_score() as i64;
+                probes += 1;
+                if best_probe
+                    .as_ref()
+                    .is_none_or(|best| probe.get_score() > best.get_score())
+                {
+                    best_probe = Some(probe);
+                }
+            }
+            scores.push((total_score, probes as u64));
+
+            board.unplay(undo);
+        }
 
-pub fn _solve(initial_state: &Board) -> (String, u32) {
-    let mut best_probe = initial_state.clone();
+        (scores, best_probe)
+    }
+}
+
+fn _solve<const W: usize, const H: usize, const C: usize>(
+    initial_state: &Board<W, H, C>,
+    k: usize,
+    threads: usize,
+    seed: u64,
+    endgame: usize,
+    budget: &Budget,
+) -> Solution {
+    let mut best_probe: Option<Board<W, H, C>> = None;
     let mut board = initial_state.clone();
-    let k = 3000;
 
-    let mut cache_region: LruCache<Board, Vec<Region>> =
-        LruCache::new(NonZeroUsize::new(1000000).unwrap());
+    let mut workers: Vec<Worker> = (0..threads)
+        .map(|i| Worker::new(seed, i, CACHE_SIZE / threads, endgame))
+        .collect();
 
     let mut depth = 1;
     loop {
@@ -21,65 +149,121 @@ pub fn _solve(initial_state: &Board) -> (String, u32) {
         eprintln!("Depth: {}", depth);
         if all_regions.is_empty() {
             break;
+        } else if budget.is_expired() {
+            // out of time: the best probe is a complete game, the board is not,
+            // so without one the board is finished by a single rollout
+            eprintln!("Time out at depth {}", depth);
+            board = match best_probe.take() {
+                Some(probe) => probe,
+                None => {
+                    let worker = &mut workers[0];
+                    rollout(
+                        &board,
+                        &mut worker.cache,
+                        &mut worker.endgame,
+                        budget,
+                        &mut worker.rng,
+                    )
+                }
+            };
+            break;
         } else if all_regions.len() == 1 {
             let region = all_regions.first().unwrap();
             board.play_region(region);
         } else {
-            let mut highest_average_score = 0;
-            let mut local_best_board = board.clone();
-            for region in all_regions {
-                let mut copy = board.clone();
-                copy.play_region(&region);
-
-                let mut average_score = 0;
-                for _ in 0..k {
-                    let probe = rollout(&copy, &mut cache_region);
-                    average_score += probe.get_score();
-                    if probe.get_score() > best_probe.get_score() {
-                        best_probe = probe;
-                    }
-                }
-
+            // the remaining time is shared evenly among the moves left
+            let depth_budget = budget.split(moves_left(&board));
+
+            // every thread runs its share of the rollouts of every child
+            let results: Vec<(ChildScores, Option<Board<W, H, C>>)> = thread::scope(|s| {
+                let handles: Vec<_> = workers
+                    .iter_mut()
+                    .enumerate()
+                    .map(|(i, worker)| {
+                        let rollouts = k / threads + usize::from(i < k % threads);
+                        let (board, all_regions, depth_budget) =
+                            (&board, &all_regions, &depth_budget);
+                        s.spawn(move || worker.evaluate(board, all_regions, rollouts, depth_budget))
+                    })
+                    .collect();
+                handles.into_iter().map(|h| h.join().unwrap()).collect()
+            });
+
+            let mut highest_average_score = i64::MIN;
+            let mut best_region = 0;
+            for i in 0..all_regions.len() {
+                let (total_score, probes) = results.iter().fold((0, 0), |(t, p), (scores, _)| {
+                    (t + scores[i].0, p + scores[i].1)
+                });
+
+                let average_score = total_score / probes.max(1) as i64;
                 if average_score > highest_average_score {
                     highest_average_score = average_score;
-                    local_best_board = copy;
+                    best_region = i;
+                }
+            }
+
+            for (_, probe) in results {
+                if let Some(probe) = probe {
+                    if best_probe
+                        .as_ref()
+                        .is_none_or(|best| probe.get_score() > best.get_score())
+                    {
+                        best_probe = Some(probe);
+                    }
                 }
             }
 
             eprintln!("Highest average score: {}", highest_average_score);
-            board = local_best_board;
+            board.play_region(&all_regions[best_region]);
         }
         eprintln!("{:?}", board);
-        eprintln!("Best Probe score: {}", best_probe.get_score());
+        if let Some(best_probe) = &best_probe {
+            eprintln!("Best Probe score: {}", best_probe.get_score());
+        }
 
         depth += 1;
     }
 
-    if best_probe.get_score() > board.get_score() {
-        board = best_probe;
+    if let Some(probe) = best_probe {
+        if probe.get_score() > board.get_score() {
+            board = probe;
+        }
     }
 
-    (board.get_actions_str(), board.get_score())
+    Solution::from_board(&board)
 }
 
-fn rollout(board: &Board, cache: &mut LruCache<Board, Vec<Region>>) -> Board {
+/// Random game from `board`, finished by the exact `endgame` search once few
+/// enough cells are left, if it completes within `budget`.
+pub(crate) fn rollout<const W: usize, const H: usize, const C: usize, R: Rng>(
+    board: &Board<W, H, C>,
+    cache: &mut LruCache<u64, Vec<Region>>,
+    endgame: &mut Endgame,
+    budget: &Budget,
+    rng: &mut R,
+) -> Board<W, H, C> {
     let mut copy = board.clone();
-    let mut rng = rand::thread_rng();
 
     loop {
-        let all_regions = cache.get_or_insert(copy.clone(), || copy.compute_all_regions());
+        if let Some(finish) = endgame.solve(&copy, budget) {
+            copy = finish;
+            break;
+        }
+
+        let all_regions = cache.get_or_insert(copy.zobrist(), || copy.compute_all_regions());
         if all_regions.is_empty() {
             break;
         }
 
-        let mut count_color = [0u8; 5];
+        let mut count_color = [0u16; C];
         for region in all_regions.iter() {
-            count_color[region.color as usize] += region.len() as u8;
+            count_color[region.color as usize] += region.len() as u16;
         }
 
-        let p = get_probs(&count_color);
+        let p = get_probs(&count_color, W * H);
 
-        let color_to_pick = pick_index(&p);
+        let color_to_pick = pick_index(&p, rng);
 
         let all_region_of_color: Vec<&Region> = all_regions
             .iter()
@@ -96,8 +280,15 @@ fn rollout(board: &Board, cache: &mut LruCache<Board, Vec<Region>>) -> Board {
     copy
 }
 
-fn get_probs(colors: &[u8; 5]) -> [f32; 5] {
-    let mut ans = [0f32; 5];
+/// Expected number of moves left, from the cells left on the board.
+fn moves_left<const W: usize, const H: usize, const C: usize>(board: &Board<W, H, C>) -> usize {
+    (board.remaining_cells() / CELLS_PER_MOVE).max(1)
+}
+
+/// Probability of each color, from the number of its cells in regions out of
+/// the `cells` of the board.
+fn get_probs<const C: usize>(colors: &[u16; C], cells: usize) -> [f32; C] {
+    let mut ans = [0f32; C];
 
     let color_float: Vec<(usize, f32)> = colors
         .iter()
@@ -107,14 +298,19 @@ fn get_probs(colors: &[u8; 5]) -> [f32; 5] {
         .collect();
 
     if color_float.len() == 1 {
-        let (i, __iterator_get_unchecked) = color_float[0];
+        let (i, _) = color_float[0];
         ans[i] = 1.0;
         return ans;
     }
 
     let beta: f32 = 4.0;
-    let alpha: f32 = 1.0_f32 + (beta / 225.0) * color_float.iter().map(|(_, x)| *x).sum::<f32>();
-    let theta = *colors.iter().min().unwrap() as f32 / 2.0;
+    let alpha: f32 =
+        1.0_f32 + (beta / cells as f32) * color_float.iter().map(|(_, x)| *x).sum::<f32>();
+    let theta = color_float
+        .iter()
+        .map(|(_, x)| *x)
+        .fold(f32::INFINITY, f32::min)
+        / 2.0;
 
     let j: Vec<(usize, f32)> = color_float
         .iter()
@@ -130,9 +326,8 @@ fn get_probs(colors: &[u8; 5]) -> [f32; 5] {
     ans
 }
 
-fn pick_index(probabilities: &[f32]) -> i8 {
+fn pick_index<R: Rng>(probabilities: &[f32], rng: &mut R) -> i8 {
     // Step 1: Generate a random number between 0 and 1
-    let mut rng = rand::thread_rng();
     let random_value: f32 = rng.gen(); // Generates a float between 0 and 1
 
     // Step 2: Create the cumulative distribution
@@ -146,18 +341,69 @@ fn pick_index(probabilities: &[f32]) -> i8 {
     }
 
     // Fallback, this should rarely happen if the probabilities sum to 1
-    4
+    (probabilities.len() - 1) as i8
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::test_util::mixed_board;
+
+    #[test]
+    fn test_reproducible() {
+        let board = mixed_board();
+
+        for threads in [1, 3] {
+            let first = _solve(&board, 10, threads, 42, ENDGAME_CELLS, &Budget::default());
+            let second = _solve(&board, 10, threads, 42, ENDGAME_CELLS, &Budget::default());
+
+            assert_eq!(first.actions, second.actions);
+            assert_eq!(first.score, second.score);
+        }
+    }
+
+    #[test]
+    fn test_expired_budget() {
+        let board = mixed_board();
+        let solution = _solve(&board, 10, 1, 42, ENDGAME_CELLS, &Budget::from_secs(0.0));
+
+        let replayed = crate::verify::replay(&board, &solution.actions).unwrap();
+        assert!(!solution.actions.is_empty());
+        assert_eq!(replayed.get_score(), solution.score);
+        assert!(replayed.compute_all_regions().is_empty());
+    }
+
+    #[test]
+    fn test_moves_left() {
+        let board = mixed_board();
+        assert_eq!(moves_left(&board), 225 / CELLS_PER_MOVE);
+
+        let empty: Board = Board::new([[-1; 15]; 15]);
+        assert_eq!(moves_left(&empty), 1);
+    }
+
+    #[test]
+    fn test_probs_absent_color() {
+        // an absent color must not lower theta: the same counts with and
+        // without an empty color give the same probabilities
+        let with_absent = get_probs(&[6, 3, 0, 3, 8], 225);
+        let without = get_probs(&[6, 3, 3, 8], 225);
+
+        assert_eq!(with_absent[2], 0.0);
+        let present = [
+            with_absent[0],
+            with_absent[1],
+            with_absent[3],
+            with_absent[4],
+        ];
+        assert_eq!(present, without);
+    }
 
     #[test]
     fn test_probs() {
-        let colors: [u8; 5] = [45, 45, 45, 45, 45];
+        let colors: [u16; 5] = [45, 45, 45, 45, 45];
 
-        let p = get_probs(&colors);
+        let p = get_probs(&colors, 225);
 
         assert_eq!(p, [0.2, 0.2, 0.2, 0.2, 0.2]);
 
@@ -166,9 +412,9 @@ mod tests {
 
     #[test]
     fn test_probs2() {
-        let colors: [u8; 5] = [225, 0, 0, 0, 0];
+        let colors: [u16; 5] = [225, 0, 0, 0, 0];
 
-        let p = get_probs(&colors);
+        let p = get_probs(&colors, 225);
 
         eprintln!("{:?}", p);
 
@@ -177,9 +423,9 @@ mod tests {
 
     #[test]
     fn test_probs6() {
-        let colors: [u8; 5] = [90, 90, 0, 0, 0];
+        let colors: [u16; 5] = [90, 90, 0, 0, 0];
 
-        let p = get_probs(&colors);
+        let p = get_probs(&colors, 225);
 
         eprintln!("{:?}", p);
 
@@ -188,9 +434,9 @@ mod tests {
 
     #[test]
     fn test_probs3() {
-        let colors: [u8; 5] = [60, 30, 30, 30, 75];
+        let colors: [u16; 5] = [60, 30, 30, 30, 75];
 
-        let p = get_probs(&colors);
+        let p = get_probs(&colors, 225);
 
         assert_eq!(p.iter().sum::<f32>(), 1.0);
 
@@ -199,9 +445,9 @@ mod tests {
 
     #[test]
     fn test_probs4() {
-        let colors: [u8; 5] = [6, 3, 3, 3, 8];
+        let colors: [u16; 5] = [6, 3, 3, 3, 8];
 
-        let p = get_probs(&colors);
+        let p = get_probs(&colors, 225);
 
         assert_eq!(p.iter().sum::<f32>(), 1.0);
 
@@ -210,9 +456,9 @@ mod tests {
 
     #[test]
     fn test_probs5() {
-        let colors: [u8; 5] = [42, 0, 0, 0, 0];
+        let colors: [u16; 5] = [42, 0, 0, 0, 0];
 
-        let p = get_probs(&colors);
+        let p = get_probs(&colors, 225);
 
         eprintln!("{:?}", p);
 
@@ -0,0 +1,619 @@
+//! Single-player MCTS shared by `hgsts` and `tabucolor`: an index-based tree,
+//! the modified UCT of Schadd et al with a weighted top score, and the
+//! TabuColor rollout.
+
+use lru::LruCache;
+use rand::Rng;
+
+use crate::{
+    bitboard::BitBoard, board::Board, endgame::Endgame, game::Game, region::Region,
+    scoring::ScoringRule, solver::Budget,
+};
+
+/// Results of the rollouts below a node.
+#[derive(Debug, Clone, Copy)]
+pub struct Stats {
+    pub visited: f64,
+    pub top_score: f64,
+    pub sum_score: f64,
+    pub sum_squared_score: f64,
+}
+
+impl Default for Stats {
+    fn default() -> Stats {
+        Stats {
+            visited: 0.0,
+            // scores may be negative, the first result is always the top one
+            top_score: f64::NEG_INFINITY,
+            sum_score: 0.0,
+            sum_squared_score: 0.0,
+        }
+    }
+}
+
+impl Stats {
+    pub fn add(&mut self, score: f64) {
+        self.sum_score += score;
+        self.sum_squared_score += score * score;
+        self.visited += 1.0;
+        if score > self.top_score {
+            self.top_score = score;
+        }
+    }
+}
+
+/// Constants of the selection value.
+#[derive(Debug, Clone, Copy)]
+pub struct Uct {
+    pub exploration: f64,
+    pub deviation: f64,
+    /// Share of the top score in the value, the mean having the rest.
+    pub top_score_weight: f64,
+}
+
+impl Uct {
+    /// Modified UCT of Schadd et al: the usual UCB1 value plus a term based on
+    /// the variance of the results, since in a single-player game a high
+    /// deviation means a promising, not a risky, move. The mean is mixed with
+    /// the top score, the only result that counts at the end.
+    ///
+    /// `virtual_loss` counts the simulations in flight below the node: they
+    /// are seen as visits that scored 0 until they are backpropagated.
+    pub fn value(&self, stats: &Stats, virtual_loss: f64, parent_visited: f64) -> f64 {
+        let visited = stats.visited + virtual_loss;
+        if visited == 0.0 {
+            return f64::MAX;
+        }
+
+        let x_bar = stats.sum_score / visited;
+        // a node only visited by simulations in flight has no top score yet
+        let top_score = if stats.visited > 0.0 {
+            stats.top_score
+        } else {
+            x_bar
+        };
+        let value = (1.0 - self.top_score_weight) * x_bar + self.top_score_weight * top_score;
+        let exploration = self.exploration * (parent_visited.ln() / visited).sqrt();
+        let deviation =
+            ((stats.sum_squared_score - visited * x_bar * x_bar + self.deviation) / visited).sqrt();
+
+        value + exploration + deviation
+    }
+
+    /// The constants of the solvers are tuned to the CodinGame points: this
+    /// scales them to the rule of `board`, by the ratio of its upper bound to
+    /// the CodinGame one, the deviation being a squared score.
+    pub fn scaled_to<const W: usize, const H: usize, const C: usize>(
+        &self,
+        board: &Board<W, H, C>,
+    ) -> Uct {
+        let codingame = ScoringRule::CodinGame.upper_bound(&board.get_color_counts());
+        let scale = if codingame > 0 {
+            board.upper_bound().max(1) as f64 / codingame as f64
+        } else {
+            1.0
+        };
+
+        Uct {
+            exploration: self.exploration * scale,
+            deviation: self.deviation * scale * scale,
+            top_score_weight: self.top_score_weight,
+        }
+    }
+}
+
+/// How `search` grows its tree and plays its rollouts.
+#[derive(Debug, Clone, Copy)]
+pub struct Settings {
+    pub uct: Uct,
+    /// A node is expanded once it has been visited that many times.
+    pub visits_before_expand: f64,
+    /// Chance of each rollout move to allow the tabu color.
+    pub chance_tabu_color: f64,
+    /// Branch and bound: skip the subtrees whose bound does not beat the best
+    /// probe.
+    pub prune: bool,
+    /// Play the rollouts on `BitBoard` instead of `Board`.
+    pub bitboard: bool,
+}
+
+pub struct Node<const W: usize, const H: usize, const C: usize> {
+    pub board: Board<W, H, C>,
+    pub parent: Option<usize>,
+    pub childs: Vec<usize>,
+    pub stats: Stats,
+    // score the node can still reach, `i32::MIN` once its subtree is cut
+    pub bound: i32,
+    pub leaf: bool,
+}
+
+impl<const W: usize, const H: usize, const C: usize> Node<W, H, C> {
+    fn new(board: Board<W, H, C>, parent: Option<usize>) -> Node<W, H, C> {
+        Node {
+            bound: board.get_score() + board.upper_bound(),
+            board,
+            parent,
+            childs: vec![],
+            stats: Stats::default(),
+            leaf: false,
+        }
+    }
+
+    pub fn is_expanded(&self) -> bool {
+        !self.childs.is_empty()
+    }
+}
+
+/// Index-based tree: nodes refer to their parent and children by position in
+/// `nodes`, so the tree can grow without any borrow of the other nodes.
+pub struct Tree<const W: usize, const H: usize, const C: usize> {
+    pub nodes: Vec<Node<W, H, C>>,
+}
+
+impl<const W: usize, const H: usize, const C: usize> Tree<W, H, C> {
+    pub fn new(board: &Board<W, H, C>) -> Tree<W, H, C> {
+        Tree {
+            nodes: vec![Node::new(board.clone(), None)],
+        }
+    }
+
+    /// Child with the best UCT value among those whose bound is above
+    /// `cutoff`, `None` when every child is cut.
+    pub fn select_child(&self, index: usize, uct: &Uct, cutoff: Option<i32>) -> Option<usize> {
+        let node = &self.nodes[index];
+        let mut childs = node
+            .childs
+            .iter()
+            .copied()
+            .filter(|&child| cutoff.is_none_or(|cutoff| self.nodes[child].bound > cutoff))
+            .peekable();
+
+        let mut best_child = *childs.peek()?;
+        let mut best_uct = f64::MIN;
+        for child in childs {
+            let uct = uct.value(&self.nodes[child].stats, 0.0, node.stats.visited);
+            if uct > best_uct {
+                best_uct = uct;
+                best_child = child;
+            }
+        }
+
+        Some(best_child)
+    }
+
+    /// Walks down from the root to a node that is not expanded, skipping the
+    /// children whose bound is not above `cutoff`. A node whose children are
+    /// all skipped is cut in turn and the walk starts over; `None` means the
+    /// whole tree is cut.
+    pub fn select(&mut self, uct: &Uct, cutoff: Option<i32>) -> Option<usize> {
+        let mut current = 0;
+
+        while self.nodes[current].is_expanded() {
+            match self.select_child(current, uct, cutoff) {
+                Some(child) => current = child,
+                None if current == 0 => return None,
+                None => {
+                    self.nodes[current].bound = i32::MIN;
+                    current = 0;
+                }
+            }
+        }
+
+        Some(current)
+    }
+
+    /// Adds a child for every move, or marks the node as a leaf.
+    pub fn expand(&mut self, index: usize) {
+        let all_regions = self.nodes[index].board.compute_all_regions();
+        if all_regions.is_empty() {
+            self.nodes[index].leaf = true;
+        }
+
+        for region in all_regions.iter() {
+            let mut copy = self.nodes[index].board.clone();
+            copy.play_region(region);
+            self.nodes.push(Node::new(copy, Some(index)));
+            let child = self.nodes.len() - 1;
+            self.nodes[index].childs.push(child);
+        }
+    }
+
+    pub fn backpropagate(&mut self, index: usize, score: f64) {
+        let mut current = Some(index);
+        while let Some(i) = current {
+            let node = &mut self.nodes[i];
+            node.stats.add(score);
+            current = node.parent;
+        }
+    }
+}
+
+/// Runs up to `iterations` selection, expansion, rollout and backpropagation
+/// steps from `initial_state` and returns the best rollout with the number of
+/// steps run. The search stops early when the budget is expired or when
+/// pruning cuts the whole tree, the best probe being then optimal. The UCT
+/// constants are scaled to the rule of `initial_state`.
+pub fn search<const W: usize, const H: usize, const C: usize, R: Rng>(
+    initial_state: &Board<W, H, C>,
+    settings: &Settings,
+    iterations: usize,
+    budget: &Budget,
+    cache: &mut LruCache<u64, Vec<Region>>,
+    endgame: &mut Endgame,
+    rng: &mut R,
+) -> (Board<W, H, C>, usize) {
+    let settings = &Settings {
+        uct: settings.uct.scaled_to(initial_state),
+        ..*settings
+    };
+    let mut tree = Tree::new(initial_state);
+    let mut best_probe: Option<Board<W, H, C>> = None;
+
+    let mut done = 0;
+    for i in 0..iterations {
+        // always run one simulation so that a complete game is returned
+        if i > 0 && budget.is_expired() {
+            break;
+        }
+        done += 1;
+
+        // selection, with branch and bound against the best probe
+        let cutoff = best_probe
+            .as_ref()
+            .filter(|_| settings.prune)
+            .map(|best| best.get_score());
+        let Some(mut node) = tree.select(&settings.uct, cutoff) else {
+            eprintln!("Every move is cut, the best probe is optimal");
+            break;
+        };
+
+        // expansion
+        if !tree.nodes[node].leaf && tree.nodes[node].stats.visited >= settings.visits_before_expand
+        {
+            tree.expand(node);
+            if tree.nodes[node].is_expanded() {
+                match tree.select_child(node, &settings.uct, cutoff) {
+                    Some(child) => node = child,
+                    None => {
+                        tree.nodes[node].bound = i32::MIN;
+                        continue;
+                    }
+                }
+            }
+        }
+
+        // simulation; top score: keep the sequence that reached it, not just
+        // the value; scores may be negative, so the first probe is always kept
+        let board = &tree.nodes[node].board;
+        let (score, probe) = simulate(board, settings, cache, endgame, budget, rng, |score| {
+            best_probe
+                .as_ref()
+                .is_none_or(|best| score > best.get_score())
+        });
+
+        // backpropagation
+        tree.backpropagate(node, score as f64);
+
+        if let Some(probe) = probe {
+            best_probe = Some(probe);
+        }
+    }
+
+    (best_probe.unwrap_or_else(|| initial_state.clone()), done)
+}
+
+/// Rollout from `board` on the backend chosen by `settings`, finished by the
+/// exact `endgame` search once few enough cells are left if it completes
+/// within `budget`: returns its score and, when `keep` wants it for that
+/// score, the game as a `Board`, since a `BitBoard` game has to be replayed to
+/// become one.
+pub fn simulate<const W: usize, const H: usize, const C: usize, R: Rng>(
+    board: &Board<W, H, C>,
+    settings: &Settings,
+    cache: &mut LruCache<u64, Vec<Region>>,
+    endgame: &mut Endgame,
+    budget: &Budget,
+    rng: &mut R,
+    keep: impl FnOnce(i32) -> bool,
+) -> (i32, Option<Board<W, H, C>>) {
+    if settings.bitboard {
+        simulate_on::<W, H, C, BitBoard<W, H, C>, R>(
+            board, settings, cache, endgame, budget, rng, keep,
+        )
+    } else {
+        simulate_on::<W, H, C, Board<W, H, C>, R>(
+            board, settings, cache, endgame, budget, rng, keep,
+        )
+    }
+}
+
+fn simulate_on<const W: usize, const H: usize, const C: usize, G: Game<W, H, C>, R: Rng>(
+    board: &Board<W, H, C>,
+    settings: &Settings,
+    cache: &mut LruCache<u64, Vec<Region>>,
+    endgame: &mut Endgame,
+    budget: &Budget,
+    rng: &mut R,
+    keep: impl FnOnce(i32) -> bool,
+) -> (i32, Option<Board<W, H, C>>) {
+    let chance_tabu_color = settings.chance_tabu_color;
+    let probe = rollout(
+        G::from_board(board),
+        cache,
+        chance_tabu_color,
+        Some(endgame),
+        rng,
+    );
+    if !endgame.applies(&probe) {
+        let score = probe.get_score();
+        return (score, keep(score).then(|| probe.into_board(board)));
+    }
+
+    // the rollout stopped for the endgame, and goes on without it if the
+    // search does not complete
+    let start = probe.into_board(board);
+    let probe = endgame
+        .solve(&start, budget)
+        .unwrap_or_else(|| rollout(start, cache, chance_tabu_color, None, rng));
+    let score = probe.get_score();
+    (score, keep(score).then_some(probe))
+}
+
+/// TabuColorRandom policy: the color with the most cells in regions at the
+/// start of the simulation is only played when no other color has a region
+/// left, or with a chance of `chance_tabu_color` at each move. Stops at the
+/// first position where `endgame` applies, if any.
+pub fn rollout<const W: usize, const H: usize, const C: usize, G: Game<W, H, C>, R: Rng>(
+    mut copy: G,
+    cache: &mut LruCache<u64, Vec<Region>>,
+    chance_tabu_color: f64,
+    endgame: Option<&Endgame>,
+    rng: &mut R,
+) -> G {
+    // the most frequent color is tabu: keeping it lets it merge into big regions
+    let mut count_color = [0usize; C];
+    for region in cache
+        .get_or_insert(copy.zobrist(), || copy.compute_all_regions())
+        .iter()
+    {
+        count_color[region.color as usize] += region.len();
+    }
+    let tabu_color = (0..C).max_by_key(|&c| count_color[c]).unwrap() as i8;
+
+    loop {
+        if endgame.is_some_and(|endgame| endgame.applies(&copy)) {
+            break;
+        }
+        let all_regions = cache.get_or_insert(copy.zobrist(), || copy.compute_all_regions());
+        if all_regions.is_empty() {
+            break;
+        }
+
+        let allow_tabu = rng.gen::<f64>() < chance_tabu_color;
+        let allowed: Vec<&Region> = all_regions
+            .iter()
+            .filter(|&region| allow_tabu || region.color != tabu_color)
+            .collect();
+
+        let picked_region = if allowed.is_empty() {
+            &all_regions[rng.gen_range(0..all_regions.len())]
+        } else {
+            allowed[rng.gen_range(0..allowed.len())]
+        };
+
+        copy.play_region(picked_region);
+    }
+
+    copy
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        solver::seeded_rng,
+        test_util::{blocks, blocks_board},
+    };
+    use std::num::NonZeroUsize;
+
+    const UCT: Uct = Uct {
+        exploration: 0.5,
+        deviation: 10000.0,
+        top_score_weight: 0.0,
+    };
+
+    #[test]
+    fn test_expand() {
+        let board = blocks_board();
+        let mut tree = Tree::new(&board);
+
+        tree.expand(0);
+
+        assert_eq!(
+            tree.nodes[0].childs.len(),
+            board.compute_all_regions().len()
+        );
+        for &child in tree.nodes[0].childs.iter() {
+            assert_eq!(tree.nodes[child].parent, Some(0));
+        }
+    }
+
+    #[test]
+    fn test_backpropagate() {
+        let board = blocks_board();
+        let mut tree = Tree::new(&board);
+        tree.expand(0);
+        let child = tree.nodes[0].childs[0];
+
+        tree.backpropagate(child, 10.0);
+        tree.backpropagate(child, 20.0);
+
+        assert_eq!(tree.nodes[child].stats.visited, 2.0);
+        assert_eq!(tree.nodes[0].stats.visited, 2.0);
+        assert_eq!(tree.nodes[0].stats.sum_score, 30.0);
+        assert_eq!(tree.nodes[0].stats.sum_squared_score, 500.0);
+        assert_eq!(tree.nodes[0].stats.top_score, 20.0);
+    }
+
+    #[test]
+    fn test_unvisited_first() {
+        let board = blocks_board();
+        let mut tree = Tree::new(&board);
+        tree.expand(0);
+        let first = tree.nodes[0].childs[0];
+        tree.backpropagate(first, 5000.0);
+
+        assert_ne!(tree.select(&UCT, None), Some(first));
+    }
+
+    #[test]
+    fn test_select_cutoff() {
+        let board = blocks_board();
+        let mut tree = Tree::new(&board);
+        tree.expand(0);
+        let first = tree.nodes[0].childs[0];
+        let bound = tree.nodes[first].bound;
+
+        for &child in tree.nodes[0].childs.clone().iter().skip(1) {
+            tree.nodes[child].bound = bound - 1;
+        }
+        assert_eq!(tree.select(&UCT, Some(bound - 1)), Some(first));
+        assert_eq!(tree.select(&UCT, Some(bound)), None);
+    }
+
+    #[test]
+    fn test_top_score_weight() {
+        let board = blocks_board();
+        let mut tree = Tree::new(&board);
+        tree.expand(0);
+        let (steady, lucky) = (tree.nodes[0].childs[0], tree.nodes[0].childs[1]);
+        for &child in tree.nodes[0].childs.clone().iter().skip(2) {
+            tree.nodes[child].bound = i32::MIN;
+        }
+
+        // same visits and about the same deviation once `deviation` dominates:
+        // the mean favors `steady`, the top score `lucky`
+        for score in [100.0, 100.0, 100.0] {
+            tree.backpropagate(steady, score);
+        }
+        for score in [0.0, 135.0, 135.0] {
+            tree.backpropagate(lucky, score);
+        }
+        let uct = Uct {
+            exploration: 0.0,
+            deviation: 1e9,
+            top_score_weight: 0.0,
+        };
+
+        assert_eq!(tree.select_child(0, &uct, Some(i32::MIN)), Some(steady));
+        let uct = Uct {
+            top_score_weight: 0.5,
+            ..uct
+        };
+        assert_eq!(tree.select_child(0, &uct, Some(i32::MIN)), Some(lucky));
+    }
+
+    #[test]
+    fn test_negative_top_score() {
+        let mut stats = Stats::default();
+        stats.add(-50.0);
+        stats.add(-80.0);
+        assert_eq!(stats.top_score, -50.0);
+
+        // in flight only: no top score to mix, and no NaN
+        let uct = Uct {
+            top_score_weight: 0.5,
+            ..UCT
+        };
+        assert!(uct.value(&Stats::default(), 1.0, 2.0).is_finite());
+    }
+
+    #[test]
+    fn test_scaled_to() {
+        let board = blocks_board();
+        assert_eq!(UCT.scaled_to(&board).exploration, UCT.exploration);
+
+        let board: Board = Board::with_rule(blocks(), ScoringRule::Clickomania);
+        let scale = 225.0 / ScoringRule::CodinGame.upper_bound(&board.get_color_counts()) as f64;
+        let uct = UCT.scaled_to(&board);
+        assert_eq!(uct.exploration, UCT.exploration * scale);
+        assert_eq!(uct.deviation, UCT.deviation * scale * scale);
+    }
+
+    #[test]
+    fn test_rollout_terminal() {
+        let board = blocks_board();
+        let mut cache = LruCache::new(NonZeroUsize::new(1000).unwrap());
+
+        let probe = rollout(board.clone(), &mut cache, 0.01, None, &mut seeded_rng(0, 0));
+        assert!(probe.compute_all_regions().is_empty());
+
+        // the same game, stopped for the endgame
+        let endgame = Endgame::new(100);
+        let start = rollout(
+            board.clone(),
+            &mut cache,
+            0.01,
+            Some(&endgame),
+            &mut seeded_rng(0, 0),
+        );
+        assert!(endgame.applies(&start));
+        assert!(probe.get_actions().starts_with(start.get_actions()));
+        assert!(start.get_actions().len() < probe.get_actions().len());
+
+        // same regions in the same order: the same game on both backends
+        let mut cache = LruCache::new(NonZeroUsize::new(1000).unwrap());
+        let bit_probe = rollout(
+            BitBoard::from_board(&board),
+            &mut cache,
+            0.01,
+            None,
+            &mut seeded_rng(0, 0),
+        );
+        assert_eq!(bit_probe.get_actions(), probe.get_actions());
+        assert!(bit_probe.into_board(&board) == probe);
+    }
+
+    #[test]
+    fn test_search() {
+        let board = blocks_board();
+        let mut cache = LruCache::new(NonZeroUsize::new(1000).unwrap());
+        let settings = Settings {
+            uct: UCT,
+            visits_before_expand: 10.0,
+            chance_tabu_color: 0.0,
+            prune: false,
+            bitboard: false,
+        };
+
+        let (probe, iterations) = search(
+            &board,
+            &settings,
+            10000,
+            &Budget::default(),
+            &mut cache,
+            &mut Endgame::default(),
+            &mut seeded_rng(0, 0),
+        );
+
+        assert_eq!(iterations, 10000);
+        assert!(probe.get_score() > 0);
+        assert!(probe.compute_all_regions().is_empty());
+
+        let settings = Settings {
+            bitboard: true,
+            ..settings
+        };
+        let mut cache = LruCache::new(NonZeroUsize::new(1000).unwrap());
+        let (bit_probe, _) = search(
+            &board,
+            &settings,
+            10000,
+            &Budget::default(),
+            &mut cache,
+            &mut Endgame::default(),
+            &mut seeded_rng(0, 0),
+        );
+        assert_eq!(bit_probe.get_actions_str(), probe.get_actions_str());
+    }
+}
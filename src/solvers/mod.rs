@@ -1,2 +1,8 @@
+pub mod beam;
+pub mod hgsts;
+pub mod iterbeam;
 pub mod mcrws;
+pub mod mcts;
+pub mod nmcs;
+pub mod nrpa;
 pub mod tabucolor;
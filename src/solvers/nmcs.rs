@@ -0,0 +1,179 @@
+// https://www.ijcai.org/Proceedings/09/Papers/083.pdf
+
+use lru::LruCache;
+use std::num::NonZeroUsize;
+
+use crate::{
+    board::Board,
+    endgame::{Endgame, ENDGAME_CELLS},
+    region::Region,
+    solver::{seeded_rng, Budget, Parameters, Solution, Solver, SolverRng, DEFAULT_SEED},
+    solvers::mcrws::rollout,
+};
+
+const LEVEL: usize = 2;
+const RUNS: usize = 1;
+
+/// `--iterations` sets the number of independent searches, the `runs`
+/// parameter.
+pub struct Nmcs {
+    level: usize,
+    runs: usize,
+    seed: u64,
+    endgame: usize,
+}
+
+impl Default for Nmcs {
+    fn default() -> Nmcs {
+        Nmcs {
+            level: LEVEL,
+            runs: RUNS,
+            seed: DEFAULT_SEED,
+            endgame: ENDGAME_CELLS,
+        }
+    }
+}
+
+impl<const W: usize, const H: usize, const C: usize> Solver<W, H, C> for Nmcs {
+    fn name(&self) -> &'static str {
+        "nmcs"
+    }
+
+    fn configure(&mut self, parameters: &Parameters) {
+        self.level = parameters.get("level", self.level);
+        self.runs = parameters.get("runs", self.runs);
+        self.seed = parameters.get("seed", self.seed);
+        self.endgame = parameters.get("endgame", self.endgame);
+    }
+
+    fn solve(&mut self, initial_state: &Board<W, H, C>, budget: &Budget) -> Solution {
+        let mut cache_region: LruCache<u64, Vec<Region>> =
+            LruCache::new(NonZeroUsize::new(1000000).unwrap());
+        let mut rng = seeded_rng(self.seed, 0);
+        let mut endgame = Endgame::new(self.endgame);
+
+        let mut best_probe: Option<Board<W, H, C>> = None;
+        for run in 0..budget.iterations_or(self.runs) {
+            if run > 0 && budget.is_expired() {
+                break;
+            }
+
+            let probe = nested(
+                initial_state,
+                self.level,
+                budget,
+                &mut cache_region,
+                &mut endgame,
+                &mut rng,
+            );
+            eprintln!("Run {}: {}", run, probe.get_score());
+
+            if best_probe
+                .as_ref()
+                .is_none_or(|best| probe.get_score() > best.get_score())
+            {
+                best_probe = Some(probe);
+            }
+        }
+
+        Solution::from_board(&best_probe.unwrap_or_else(|| initial_state.clone()))
+    }
+}
+
+/// Plays every move followed by a search one level below, then moves along
+/// the best sequence found so far, which is only replaced by a better one.
+/// Once the budget is expired the best sequence is followed to the end.
+fn nested<const W: usize, const H: usize, const C: usize>(
+    board: &Board<W, H, C>,
+    level: usize,
+    budget: &Budget,
+    cache: &mut LruCache<u64, Vec<Region>>,
+    endgame: &mut Endgame,
+    rng: &mut SolverRng,
+) -> Board<W, H, C> {
+    if level == 0 {
+        return rollout(board, cache, endgame, budget, rng);
+    }
+
+    let mut position = board.clone();
+    let mut best_sequence: Option<Board<W, H, C>> = None;
+
+    loop {
+        let all_regions = position.compute_all_regions();
+        if all_regions.is_empty() {
+            break;
+        }
+
+        for region in all_regions.iter() {
+            if best_sequence.is_some() && budget.is_expired() {
+                break;
+            }
+
+            let undo = position.play_region_with_undo(region);
+            let probe = nested(&position, level - 1, budget, cache, endgame, rng);
+            position.unplay(undo);
+
+            if best_sequence
+                .as_ref()
+                .is_none_or(|best| probe.get_score() > best.get_score())
+            {
+                best_sequence = Some(probe);
+            }
+        }
+
+        // the best sequence always extends the position, it has one more move
+        let best = best_sequence.as_ref().unwrap();
+        debug_assert!(best.get_actions().starts_with(position.get_actions()));
+        debug_assert!(best.get_actions().len() > position.get_actions().len());
+        let next = best.get_actions()[position.get_actions().len()];
+        position.play_index(next);
+    }
+
+    best_sequence.unwrap_or(position)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_util::blocks_board;
+
+    #[test]
+    fn test_nested_level_1() {
+        let board = blocks_board();
+        let mut cache = LruCache::new(NonZeroUsize::new(1000).unwrap());
+
+        let probe = nested(
+            &board,
+            1,
+            &Budget::default(),
+            &mut cache,
+            &mut Endgame::default(),
+            &mut seeded_rng(0, 0),
+        );
+
+        assert!(probe.compute_all_regions().is_empty());
+
+        let mut replay = board.clone();
+        for &action in probe.get_actions() {
+            replay.play_index(action);
+        }
+        assert_eq!(replay.get_score(), probe.get_score());
+    }
+
+    #[test]
+    fn test_nested_expired_budget() {
+        let board = blocks_board();
+        let mut cache = LruCache::new(NonZeroUsize::new(1000).unwrap());
+
+        let probe = nested(
+            &board,
+            3,
+            &Budget::from_secs(0.0),
+            &mut cache,
+            &mut Endgame::default(),
+            &mut seeded_rng(0, 0),
+        );
+
+        assert!(probe.compute_all_regions().is_empty());
+    }
+}
@@ -0,0 +1,315 @@
+// https://www.ijcai.org/Proceedings/11/Papers/115.pdf
+
+use fxhash::FxHashMap;
+use lru::LruCache;
+use rand::Rng;
+use std::num::NonZeroUsize;
+
+use crate::{
+    board::Board,
+    endgame::{Endgame, ENDGAME_CELLS},
+    region::Region,
+    solver::{seeded_rng, Budget, Parameters, Solution, Solver, SolverRng, DEFAULT_SEED},
+};
+
+const LEVEL: usize = 3;
+const ITERATIONS_PER_LEVEL: usize = 100;
+const ALPHA: f64 = 1.0;
+const RUNS: usize = 1;
+
+type Policy = FxHashMap<u32, f64>;
+
+/// A playout: the final board and, for each move chosen by the policy, the
+/// codes of all the legal moves with the index of the one that was played;
+/// the moves of the endgame search that finished the board come after them.
+#[derive(Clone)]
+struct Sequence<const W: usize, const H: usize, const C: usize> {
+    board: Board<W, H, C>,
+    steps: Vec<(usize, Vec<u32>)>,
+}
+
+/// `--iterations` sets the number of independent searches, the `runs`
+/// parameter.
+pub struct Nrpa {
+    level: usize,
+    iterations_per_level: usize,
+    alpha: f64,
+    runs: usize,
+    reuse_policy: bool,
+    seed: u64,
+    endgame: usize,
+}
+
+impl Default for Nrpa {
+    fn default() -> Nrpa {
+        Nrpa {
+            level: LEVEL,
+            iterations_per_level: ITERATIONS_PER_LEVEL,
+            alpha: ALPHA,
+            runs: RUNS,
+            reuse_policy: false,
+            seed: DEFAULT_SEED,
+            endgame: ENDGAME_CELLS,
+        }
+    }
+}
+
+impl<const W: usize, const H: usize, const C: usize> Solver<W, H, C> for Nrpa {
+    fn name(&self) -> &'static str {
+        "nrpa"
+    }
+
+    fn configure(&mut self, parameters: &Parameters) {
+        self.level = parameters.get("level", self.level);
+        // every level and every run needs one iteration to return a game
+        self.iterations_per_level = parameters.get("n", self.iterations_per_level).max(1);
+        self.alpha = parameters.get("alpha", self.alpha);
+        self.runs = parameters.get("runs", self.runs).max(1);
+        self.reuse_policy = parameters.get("reuse_policy", self.reuse_policy);
+        self.seed = parameters.get("seed", self.seed);
+        self.endgame = parameters.get("endgame", self.endgame);
+    }
+
+    fn solve(&mut self, initial_state: &Board<W, H, C>, budget: &Budget) -> Solution {
+        let mut playouts = Playouts::new(self.seed, self.endgame);
+
+        let mut policy = Policy::default();
+        let mut best_probe: Option<Board<W, H, C>> = None;
+        for run in 0..budget.iterations_or(self.runs) {
+            if run > 0 && budget.is_expired() {
+                break;
+            }
+            if !self.reuse_policy {
+                policy.clear();
+            }
+
+            let sequence = self.nested(
+                initial_state,
+                self.level,
+                &mut policy,
+                budget,
+                &mut playouts,
+            );
+            eprintln!("Run {}: {}", run, sequence.board.get_score());
+
+            if best_probe
+                .as_ref()
+                .is_none_or(|best| sequence.board.get_score() > best.get_score())
+            {
+                best_probe = Some(sequence.board);
+            }
+        }
+
+        Solution::from_board(&best_probe.unwrap_or_else(|| initial_state.clone()))
+    }
+}
+
+impl Nrpa {
+    fn nested<const W: usize, const H: usize, const C: usize>(
+        &self,
+        board: &Board<W, H, C>,
+        level: usize,
+        policy: &mut Policy,
+        budget: &Budget,
+        playouts: &mut Playouts,
+    ) -> Sequence<W, H, C> {
+        if level == 0 {
+            return playouts.playout(board, policy, budget);
+        }
+
+        let mut best: Option<Sequence<W, H, C>> = None;
+        for _ in 0..self.iterations_per_level {
+            if best.is_some() && budget.is_expired() {
+                break;
+            }
+
+            let mut child_policy = policy.clone();
+            let sequence = self.nested(board, level - 1, &mut child_policy, budget, playouts);
+            if best
+                .as_ref()
+                .is_none_or(|best| sequence.board.get_score() >= best.board.get_score())
+            {
+                best = Some(sequence);
+            }
+
+            adapt(policy, best.as_ref().unwrap(), self.alpha);
+        }
+
+        best.unwrap()
+    }
+}
+
+/// Code of a move, shared by the moves that are considered the same across
+/// positions: the color, the cell the region is designated by and its size.
+fn move_code(region: &Region) -> u32 {
+    region.first_position as u32 | (region.color as u32) << 16 | (region.len().min(31) as u32) << 24
+}
+
+/// What the playouts of a run share.
+struct Playouts {
+    cache: LruCache<u64, Vec<Region>>,
+    endgame: Endgame,
+    rng: SolverRng,
+}
+
+impl Playouts {
+    fn new(seed: u64, endgame: usize) -> Playouts {
+        Playouts {
+            cache: LruCache::new(NonZeroUsize::new(1000000).unwrap()),
+            endgame: Endgame::new(endgame),
+            rng: seeded_rng(seed, 0),
+        }
+    }
+
+    /// Game from `board` following the policy, finished by the exact endgame
+    /// search once few enough cells are left if it completes within `budget`.
+    fn playout<const W: usize, const H: usize, const C: usize>(
+        &mut self,
+        board: &Board<W, H, C>,
+        policy: &Policy,
+        budget: &Budget,
+    ) -> Sequence<W, H, C> {
+        let mut copy = board.clone();
+        let mut steps = Vec::new();
+
+        loop {
+            if let Some(finish) = self.endgame.solve(&copy, budget) {
+                copy = finish;
+                break;
+            }
+
+            let all_regions = self
+                .cache
+                .get_or_insert(copy.zobrist(), || copy.compute_all_regions());
+            if all_regions.is_empty() {
+                break;
+            }
+
+            let codes: Vec<u32> = all_regions.iter().map(move_code).collect();
+            let weights: Vec<f64> = codes
+                .iter()
+                .map(|code| policy.get(code).copied().unwrap_or(0.0).exp())
+                .collect();
+
+            let mut random_value = self.rng.gen::<f64>() * weights.iter().sum::<f64>();
+            let mut picked = weights.len() - 1;
+            for (i, weight) in weights.iter().enumerate() {
+                if random_value < *weight {
+                    picked = i;
+                    break;
+                }
+                random_value -= weight;
+            }
+
+            copy.play_region(&all_regions[picked]);
+            steps.push((picked, codes));
+        }
+
+        Sequence { board: copy, steps }
+    }
+}
+
+/// Moves the policy towards the moves of `sequence`: the code played gets
+/// `alpha` more, every legal code loses `alpha` times its softmax probability.
+fn adapt<const W: usize, const H: usize, const C: usize>(
+    policy: &mut Policy,
+    sequence: &Sequence<W, H, C>,
+    alpha: f64,
+) {
+    let previous = policy.clone();
+
+    for (picked, codes) in sequence.steps.iter() {
+        let weights: Vec<f64> = codes
+            .iter()
+            .map(|code| previous.get(code).copied().unwrap_or(0.0).exp())
+            .collect();
+        let total: f64 = weights.iter().sum();
+
+        *policy.entry(codes[*picked]).or_insert(0.0) += alpha;
+        for (code, weight) in codes.iter().zip(weights.iter()) {
+            *policy.entry(*code).or_insert(0.0) -= alpha * weight / total;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_util::blocks_board;
+
+    #[test]
+    fn test_move_code() {
+        let board = blocks_board();
+        let all_regions = board.compute_all_regions();
+
+        let mut codes: Vec<u32> = all_regions.iter().map(move_code).collect();
+        codes.sort();
+        codes.dedup();
+
+        assert_eq!(codes.len(), all_regions.len());
+    }
+
+    #[test]
+    fn test_adapt() {
+        let sequence = Sequence {
+            board: blocks_board(),
+            steps: vec![(0, vec![1, 2]), (1, vec![3, 4, 5])],
+        };
+        let mut policy = Policy::default();
+
+        adapt(&mut policy, &sequence, 1.0);
+
+        assert_eq!(policy[&1], 0.5);
+        assert_eq!(policy[&2], -0.5);
+        assert!(policy[&4] > 0.0 && policy[&3] < 0.0 && policy[&5] < 0.0);
+        assert!(policy.values().sum::<f64>().abs() < 1e-9);
+    }
+
+    #[test]
+    fn test_nested() {
+        let board = blocks_board();
+        let nrpa = Nrpa {
+            level: 2,
+            iterations_per_level: 10,
+            ..Nrpa::default()
+        };
+
+        let sequence = nrpa.nested(
+            &board,
+            2,
+            &mut Policy::default(),
+            &Budget::default(),
+            &mut Playouts::new(0, 0),
+        );
+
+        assert!(sequence.board.compute_all_regions().is_empty());
+        assert_eq!(sequence.steps.len(), sequence.board.get_actions().len());
+
+        // with the endgame, the policy only chooses the first moves
+        let sequence = nrpa.nested(
+            &board,
+            2,
+            &mut Policy::default(),
+            &Budget::default(),
+            &mut Playouts::new(0, ENDGAME_CELLS),
+        );
+
+        assert!(sequence.board.compute_all_regions().is_empty());
+        assert!(sequence.steps.len() <= sequence.board.get_actions().len());
+    }
+
+    #[test]
+    fn test_configure_zero_iterations() {
+        let board = blocks_board();
+        let mut parameters = Parameters::default();
+        parameters.insert("n", "0");
+        parameters.insert("runs", "0");
+        let mut nrpa = Nrpa::default();
+        Solver::<15, 15, 5>::configure(&mut nrpa, &parameters);
+
+        let solution = nrpa.solve(&board, &Budget::default());
+
+        let replayed = crate::verify::replay(&board, &solution.actions).unwrap();
+        assert!(replayed.compute_all_regions().is_empty());
+    }
+}
@@ -1,185 +1,545 @@
 // https://project.dke.maastrichtuniversity.nl/games/files/bsc/Tak_Bsc-paper.pdf
 
 use lru::LruCache;
-use rand::Rng;
-use std::num::NonZeroUsize;
-
-use crate::{board::Board, region::Region};
-
-const UCT_CONSTANT: f32 = 5.96;
-const DEVIATION_CONSTANT: f32 = 67.98;
-const VISITS_BEFORE_EXPAND: f32 = 13.0;
-const TOP_SCORE_WEIGHT: f32 = 0.49;
-const CHANCE_CHOSEN_COLOR: f32 = 0.0007;
-
-struct Node<'a> {
-    visited: f32,
-    highest_score: f32,
-    sum_score: f32,
-    board: &'a Board,
-    childs: Vec<&'a Self>,
-    parent: Option<&'a mut Self>,
-    leaf: bool,
+use std::{
+    num::NonZeroUsize,
+    ops::Range,
+    sync::{
+        atomic::{AtomicI32, AtomicU32, AtomicU64, AtomicUsize, Ordering},
+        Mutex, OnceLock,
+    },
+    thread,
+    time::Instant,
+};
+
+use crate::{
+    board::Board,
+    endgame::{Endgame, ENDGAME_CELLS},
+    region::Region,
+    solver::{seeded_rng, Budget, Parameters, Solution, Solver, DEFAULT_SEED},
+    solvers::mcts::{self, simulate, Settings, Stats, Uct},
+};
+
+// tuned to the CodinGame points, `Uct::scaled_to` adapts them to the rule
+const UCT_CONSTANT: f64 = 5.96;
+const DEVIATION_CONSTANT: f64 = 67.98;
+// tuned as "more than 13 visits"
+const VISITS_BEFORE_EXPAND: f64 = 14.0;
+const TOP_SCORE_WEIGHT: f64 = 0.49;
+const CHANCE_CHOSEN_COLOR: f64 = 0.0007;
+const ITERATIONS: usize = 1000000;
+const THREADS: usize = 1;
+
+/// TabuColor: the tree search of `mcts` with rollouts that keep the most
+/// frequent color for the end, on one or several threads.
+/// `--iterations` sets the number of tree iterations, shared by all the
+/// threads, the `iterations` parameter.
+pub struct TabuColor {
+    settings: Settings,
+    iterations: usize,
+    threads: usize,
+    seed: u64,
+    endgame: usize,
 }
 
-impl<'a> Node<'a> {
-    pub fn new(board: &'a Board) -> Node {
-        Node {
-            visited: 0.0,
-            highest_score: 0.0,
-            sum_score: 0.0,
-            board,
-            childs: vec![],
-            parent: None,
-            leaf: false,
+impl Default for TabuColor {
+    fn default() -> TabuColor {
+        TabuColor {
+            settings: Settings {
+                uct: Uct {
+                    exploration: UCT_CONSTANT,
+                    deviation: DEVIATION_CONSTANT,
+                    top_score_weight: TOP_SCORE_WEIGHT,
+                },
+                visits_before_expand: VISITS_BEFORE_EXPAND,
+                chance_tabu_color: CHANCE_CHOSEN_COLOR,
+                prune: false,
+                bitboard: false,
+            },
+            iterations: ITERATIONS,
+            threads: THREADS,
+            seed: DEFAULT_SEED,
+            endgame: ENDGAME_CELLS,
         }
     }
+}
 
-    pub fn get_board(&self) -> &Board {
-        self.board
+impl<const W: usize, const H: usize, const C: usize> Solver<W, H, C> for TabuColor {
+    fn name(&self) -> &'static str {
+        "tabucolor"
     }
 
-    pub fn set_parent(&mut self, parent: &'a mut Self) {
-        self.parent = Some(parent);
+    fn configure(&mut self, parameters: &Parameters) {
+        let settings = &mut self.settings;
+        settings.uct.exploration = parameters.get("c", settings.uct.exploration);
+        settings.uct.deviation = parameters.get("d", settings.uct.deviation);
+        settings.uct.top_score_weight = parameters.get("top_weight", settings.uct.top_score_weight);
+        settings.visits_before_expand = parameters.get("expand", settings.visits_before_expand);
+        settings.chance_tabu_color = parameters.get("chance_color", settings.chance_tabu_color);
+        settings.prune = parameters.get("prune", settings.prune);
+        settings.bitboard = parameters.get("bitboard", settings.bitboard);
+        self.iterations = parameters.get("iterations", self.iterations);
+        self.threads = parameters.get("threads", self.threads).max(1);
+        self.seed = parameters.get("seed", self.seed);
+        self.endgame = parameters.get("endgame", self.endgame);
     }
 
-    pub fn add_child(&mut self, child: &'a Self) {
-        self.childs.push(child);
+    fn solve(&mut self, initial_state: &Board<W, H, C>, budget: &Budget) -> Solution {
+        let start = Instant::now();
+        let (best_probe, iterations) = if self.threads > 1 {
+            self.solve_parallel(initial_state, budget)
+        } else {
+            self.solve_sequential(initial_state, budget)
+        };
+
+        let elapsed = start.elapsed().as_secs_f64();
+        eprintln!(
+            "{} iterations with {} threads in {:.2}s ({:.0}/s)",
+            iterations,
+            self.threads,
+            elapsed,
+            iterations as f64 / elapsed
+        );
+
+        Solution::from_board(&best_probe)
     }
+}
 
-    pub fn is_expanded(&self) -> bool {
-        self.childs.is_empty()
+impl TabuColor {
+    fn solve_sequential<const W: usize, const H: usize, const C: usize>(
+        &self,
+        initial_state: &Board<W, H, C>,
+        budget: &Budget,
+    ) -> (Board<W, H, C>, usize) {
+        let mut cache_region: LruCache<u64, Vec<Region>> =
+            LruCache::new(NonZeroUsize::new(1000000).unwrap());
+        let mut rng = seeded_rng(self.seed, 0);
+
+        mcts::search(
+            initial_state,
+            &self.settings,
+            budget.iterations_or(self.iterations),
+            budget,
+            &mut cache_region,
+            &mut Endgame::new(self.endgame),
+            &mut rng,
+        )
     }
 
-    pub fn set_leaf(&mut self) {
-        self.leaf = true;
+    /// Tree parallelization: the threads share one tree, each one running its
+    /// own select/rollout/backpropagate loop with its own cache and generator.
+    /// The seed fixes the generators, but the interleaving of the threads
+    /// still makes runs differ.
+    fn solve_parallel<const W: usize, const H: usize, const C: usize>(
+        &self,
+        initial_state: &Board<W, H, C>,
+        budget: &Budget,
+    ) -> (Board<W, H, C>, usize) {
+        let settings = Settings {
+            uct: self.settings.uct.scaled_to(initial_state),
+            ..self.settings
+        };
+        let tree = SharedTree::new(initial_state);
+        let best_probe: Mutex<Option<Board<W, H, C>>> = Mutex::new(None);
+        // score of `best_probe`, read without the lock for the cutoff
+        let best_score = AtomicI32::new(i32::MIN);
+        let iterations = AtomicUsize::new(0);
+        let max_iterations = budget.iterations_or(self.iterations);
+
+        thread::scope(|s| {
+            for thread in 0..self.threads {
+                let (tree, best_probe, best_score, iterations) =
+                    (&tree, &best_probe, &best_score, &iterations);
+                s.spawn(move || {
+                    let mut rng = seeded_rng(self.seed, thread);
+                    let mut cache_region: LruCache<u64, Vec<Region>> =
+                        LruCache::new(NonZeroUsize::new(1000000 / self.threads).unwrap());
+                    let mut endgame = Endgame::new(self.endgame);
+
+                    loop {
+                        let i = iterations.fetch_add(1, Ordering::Relaxed);
+                        if i >= max_iterations || (i > 0 && budget.is_expired()) {
+                            break;
+                        }
+
+                        let cutoff = Some(best_score.load(Ordering::Relaxed))
+                            .filter(|&score| settings.prune && score > i32::MIN);
+                        let Some((path, board)) = tree.select_and_expand(&settings, cutoff) else {
+                            eprintln!("Every move is cut, the best probe is optimal");
+                            break;
+                        };
+                        let (score, probe) = simulate(
+                            &board,
+                            &settings,
+                            &mut cache_region,
+                            &mut endgame,
+                            budget,
+                            &mut rng,
+                            |score| score > best_score.load(Ordering::Relaxed),
+                        );
+                        tree.backpropagate(&path, score as f64);
+
+                        // another thread may have done better in the meantime
+                        let Some(probe) = probe else { continue };
+                        let mut best_probe = best_probe.lock().unwrap();
+                        if best_probe
+                            .as_ref()
+                            .is_none_or(|best| probe.get_score() > best.get_score())
+                        {
+                            best_score.store(probe.get_score(), Ordering::Relaxed);
+                            *best_probe = Some(probe);
+                        }
+                    }
+                });
+            }
+        });
+
+        let iterations = iterations.into_inner().min(max_iterations);
+        let best_probe = best_probe.into_inner().unwrap();
+        (
+            best_probe.unwrap_or_else(|| initial_state.clone()),
+            iterations,
+        )
     }
+}
 
-    pub fn backpropagate(&mut self, score: f32) {
-        self.sum_score += score;
-        self.visited += 1.0;
-        if score > self.highest_score {
-            self.highest_score = score;
-        }
+const CHUNK_BITS: usize = 16;
+const CHUNK_SIZE: usize = 1 << CHUNK_BITS;
+const MAX_CHUNKS: usize = 1 << 12;
+
+/// Append-only arena shared by the threads. Indices are reserved with an
+/// atomic counter, each chunk is allocated by the first thread that needs it
+/// and each slot is written once, so reading a node takes no lock.
+struct Arena<T> {
+    chunks: Vec<OnceLock<Box<[OnceLock<T>]>>>,
+    len: AtomicUsize,
+}
 
-        if let Some(ref mut node) = self.parent {
-            node.backpropagate(score);
+impl<T> Arena<T> {
+    fn new() -> Arena<T> {
+        Arena {
+            chunks: (0..MAX_CHUNKS).map(|_| OnceLock::new()).collect(),
+            len: AtomicUsize::new(0),
         }
     }
 
-    pub fn get_child(&self) -> Option<&'a Node> {
-        let mut best_child: Option<&Node> = None;
-        let mut best_uct = 0.0f32;
-        for &child in self.childs.iter() {
-            let x_bar = if child.visited > 0.0 {
-                child.sum_score / child.visited
-            } else {
-                0.0f32
-            };
+    #[cfg(test)]
+    fn len(&self) -> usize {
+        self.len.load(Ordering::Acquire)
+    }
 
-            let p1 = (self.visited.log2() / child.visited).sqrt();
-            let p2 = if self.is_expanded() {
-                let a: f32 = self.childs.iter().map(|x| x.sum_score * x.sum_score).sum();
-                let b = self.visited * x_bar * x_bar;
-                ((a + b + DEVIATION_CONSTANT) / self.visited).sqrt()
-            } else {
-                f32::MAX
-            };
-            let uct = x_bar + p1 + p2;
-            if uct > best_uct {
-                best_uct = uct;
-                best_child = Some(child)
+    /// Appends `items` at consecutive indices and returns them.
+    fn extend(&self, items: Vec<T>) -> Range<usize> {
+        let count = items.len();
+        let first = self.len.fetch_add(count, Ordering::AcqRel);
+        for (index, item) in (first..).zip(items) {
+            let chunk = self.chunks[index >> CHUNK_BITS]
+                .get_or_init(|| (0..CHUNK_SIZE).map(|_| OnceLock::new()).collect());
+            if chunk[index & (CHUNK_SIZE - 1)].set(item).is_err() {
+                unreachable!("arena slot {} written twice", index);
             }
         }
+        first..first + count
+    }
 
-        best_child
+    /// The item at `index`, which must have been returned by `extend`.
+    fn get(&self, index: usize) -> &T {
+        self.chunks[index >> CHUNK_BITS]
+            .get()
+            .and_then(|chunk| chunk[index & (CHUNK_SIZE - 1)].get())
+            .expect("arena index not written yet")
     }
 }
 
-pub fn _solve(initial_state: &Board) -> (String, u32) {
-    let mut best_probe = initial_state.clone();
-    let board = initial_state.clone();
+/// `Stats` updated without a lock, the `f64` fields being kept as their bits.
+/// A reader may see a visit before its score, which the selection tolerates
+/// as it does the virtual losses.
+struct AtomicStats {
+    visited: AtomicU64,
+    top_score: AtomicU64,
+    sum_score: AtomicU64,
+    sum_squared_score: AtomicU64,
+}
 
-    let mut cache_region: LruCache<Board, Vec<Region>> =
-        LruCache::new(NonZeroUsize::new(1000000).unwrap());
+impl AtomicStats {
+    fn new() -> AtomicStats {
+        let stats = Stats::default();
+        AtomicStats {
+            visited: AtomicU64::new(0),
+            top_score: AtomicU64::new(stats.top_score.to_bits()),
+            sum_score: AtomicU64::new(stats.sum_score.to_bits()),
+            sum_squared_score: AtomicU64::new(stats.sum_squared_score.to_bits()),
+        }
+    }
 
-    let root = Node::new(initial_state);
+    fn visited(&self) -> f64 {
+        self.visited.load(Ordering::Relaxed) as f64
+    }
 
-    for _ in 0..1000000 {
-        let mut node = &root;
+    fn load(&self) -> Stats {
+        let load = |value: &AtomicU64| f64::from_bits(value.load(Ordering::Relaxed));
+        Stats {
+            visited: self.visited(),
+            top_score: load(&self.top_score),
+            sum_score: load(&self.sum_score),
+            sum_squared_score: load(&self.sum_squared_score),
+        }
+    }
 
-        // selection
-        while node.is_expanded() {
-            node = node.get_child().unwrap();
+    fn add(&self, score: f64) {
+        fn update(value: &AtomicU64, f: impl Fn(f64) -> Option<f64>) {
+            // `Err` only means that `f` left the value unchanged
+            let _ = value.fetch_update(Ordering::Relaxed, Ordering::Relaxed, |bits| {
+                f(f64::from_bits(bits)).map(f64::to_bits)
+            });
         }
 
-        // rollout
-        let probe = rollout(&board, &mut cache_region);
+        update(&self.sum_score, |sum| Some(sum + score));
+        update(&self.sum_squared_score, |sum| Some(sum + score * score));
+        update(&self.top_score, |top| (score > top).then_some(score));
+        self.visited.fetch_add(1, Ordering::Relaxed);
+    }
+}
 
-        // backpropagate
-        let score = probe.get_score() as f32;
-        node.backpropagate(score);
+struct SharedNode<const W: usize, const H: usize, const C: usize> {
+    board: Board<W, H, C>,
+    // score the node can still reach, `i32::MIN` once its subtree is cut
+    bound: AtomicI32,
+    stats: AtomicStats,
+    virtual_loss: AtomicU32,
+    // set once, by the thread that expands the node; empty for a leaf
+    childs: OnceLock<Range<usize>>,
+}
 
-        // keep the best probe in case of better solution than the MCTS
-        if probe.get_score() > best_probe.get_score() {
-            best_probe = probe.clone();
+impl<const W: usize, const H: usize, const C: usize> SharedNode<W, H, C> {
+    fn new(board: Board<W, H, C>) -> SharedNode<W, H, C> {
+        SharedNode {
+            bound: AtomicI32::new(board.get_score() + board.upper_bound()),
+            board,
+            stats: AtomicStats::new(),
+            virtual_loss: AtomicU32::new(0),
+            childs: OnceLock::new(),
         }
+    }
 
-        // expand
-        if node.visited > VISITS_BEFORE_EXPAND {
-            let all_regions = node.get_board().compute_all_regions();
-            if all_regions.is_empty() {
-                node.set_leaf();
-            }
-            for region in all_regions.iter() {
-                let copy = node.get_board().clone();
-                copy.play_region(region);
-                node.add_child(&Node::new(&copy));
-            }
-        }
+    fn expanded_childs(&self) -> Option<Range<usize>> {
+        self.childs
+            .get()
+            .filter(|childs| !childs.is_empty())
+            .cloned()
     }
+}
 
-    (best_probe.get_actions_str(), best_probe.get_score())
+/// Tree shared by the threads: the children of a node are created together
+/// at consecutive indices of the arena, and their statistics are atomics, so
+/// that neither the selection nor the backpropagation takes a lock.
+struct SharedTree<const W: usize, const H: usize, const C: usize> {
+    nodes: Arena<SharedNode<W, H, C>>,
 }
 
-fn rollout(board: &Board, cache: &mut LruCache<Board, Vec<Region>>) -> Board {
-    let mut copy = board.clone();
-    let mut rng = rand::thread_rng();
+impl<const W: usize, const H: usize, const C: usize> SharedTree<W, H, C> {
+    fn new(board: &Board<W, H, C>) -> SharedTree<W, H, C> {
+        let nodes = Arena::new();
+        nodes.extend(vec![SharedNode::new(board.clone())]);
+        SharedTree { nodes }
+    }
 
-    loop {
-        let all_regions = cache.get_or_insert(copy.clone(), || copy.compute_all_regions());
-        if all_regions.is_empty() {
-            break;
-        }
+    fn get(&self, index: usize) -> &SharedNode<W, H, C> {
+        self.nodes.get(index)
+    }
 
-        let mut count_color = [0u8; 5];
-        for region in all_regions.iter() {
-            count_color[region.color as usize] += region.len() as u8;
-        }
+    /// Walks down the tree adding a virtual loss to every node on the way,
+    /// expands the last one if it was visited enough, and returns the path
+    /// with the board to start the rollout from. Children whose bound is not
+    /// above `cutoff` are skipped, `None` is returned when all the children of
+    /// the root are.
+    fn select_and_expand(
+        &self,
+        settings: &Settings,
+        cutoff: Option<i32>,
+    ) -> Option<(Vec<usize>, Board<W, H, C>)> {
+        let mut path = vec![0];
+        let mut node = self.get(0);
+        node.virtual_loss.fetch_add(1, Ordering::Relaxed);
+
+        loop {
+            let childs = match node.expanded_childs() {
+                Some(childs) => childs,
+                None => {
+                    let visited = node.stats.visited();
+                    if visited < settings.visits_before_expand {
+                        break;
+                    }
+                    let childs = self.expand(node);
+                    if childs.is_empty() {
+                        break;
+                    }
+                    childs
+                }
+            };
 
-        let p = get_probs(&count_color);
+            let parent_visited =
+                node.stats.visited() + node.virtual_loss.load(Ordering::Relaxed) as f64;
+            let mut best = None;
+            let mut best_uct = f64::MIN;
+            for child in childs {
+                let child_node = self.get(child);
+                let bound = child_node.bound.load(Ordering::Relaxed);
+                if cutoff.is_some_and(|cutoff| bound <= cutoff) {
+                    continue;
+                }
+                let stats = child_node.stats.load();
+                let virtual_loss = child_node.virtual_loss.load(Ordering::Relaxed) as f64;
+                let uct = settings.uct.value(&stats, virtual_loss, parent_visited);
+                if best.is_none() || uct > best_uct {
+                    best_uct = uct;
+                    best = Some(child);
+                }
+            }
 
-        let color_to_pick = pick_index(&p);
+            match best {
+                Some(child) => {
+                    node = self.get(child);
+                    node.virtual_loss.fetch_add(1, Ordering::Relaxed);
+                    path.push(child);
+                }
+                None if path.len() == 1 => {
+                    node.virtual_loss.fetch_sub(1, Ordering::Relaxed);
+                    return None;
+                }
+                None => {
+                    // every child is cut: so is the node, after this last rollout
+                    node.bound.store(i32::MIN, Ordering::Relaxed);
+                    break;
+                }
+            }
+        }
 
-        let all_region_of_color: Vec<&Region> = all_regions
-            .iter()
-            .filter(|&region| region.color == color_to_pick)
-            .collect();
+        Some((path, node.board.clone()))
+    }
 
-        if !all_region_of_color.is_empty() {
-            let picked_region = rng.gen_range(0..all_region_of_color.len());
+    fn expand(&self, node: &SharedNode<W, H, C>) -> Range<usize> {
+        let childs = node.childs.get_or_init(|| {
+            let new_nodes: Vec<SharedNode<W, H, C>> = node
+                .board
+                .compute_all_regions()
+                .iter()
+                .map(|region| {
+                    let mut copy = node.board.clone();
+                    copy.play_region(region);
+                    SharedNode::new(copy)
+                })
+                .collect();
+            self.nodes.extend(new_nodes)
+        });
+
+        childs.clone()
+    }
 
-            copy.play_region(all_region_of_color[picked_region]);
+    fn backpropagate(&self, path: &[usize], score: f64) {
+        for &index in path.iter().rev() {
+            let node = self.get(index);
+            node.stats.add(score);
+            node.virtual_loss.fetch_sub(1, Ordering::Relaxed);
         }
     }
-
-    copy
 }
 
-// #[cfg(test)]
-// mod tests {
-//     use super::*;
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_util::blocks_board;
+
+    #[test]
+    fn test_parallel() {
+        let board = blocks_board();
+        let tabucolor = TabuColor {
+            threads: 4,
+            ..TabuColor::default()
+        };
+
+        let budget = Budget::default().with_iterations(Some(200));
+        let (probe, iterations) = tabucolor.solve_parallel(&board, &budget);
+        assert_eq!(iterations, 200);
+        assert!(probe.compute_all_regions().is_empty());
+
+        let tree = SharedTree::new(&board);
+        let childs = tree.expand(tree.get(0));
+        assert_eq!(tree.nodes.len(), board.compute_all_regions().len() + 1);
+        assert_eq!(childs, 1..tree.nodes.len());
+        assert_eq!(tree.expand(tree.get(0)), childs);
+
+        let path = vec![0, childs.start];
+        tree.get(0).virtual_loss.fetch_add(1, Ordering::Relaxed);
+        tree.get(childs.start)
+            .virtual_loss
+            .fetch_add(1, Ordering::Relaxed);
+        tree.backpropagate(&path, 4.0);
+        for index in path {
+            let node = tree.get(index);
+            let stats = node.stats.load();
+            assert_eq!(stats.visited, 1.0);
+            assert_eq!(stats.top_score, 4.0);
+            assert_eq!(stats.sum_squared_score, 16.0);
+            assert_eq!(node.virtual_loss.load(Ordering::Relaxed), 0);
+        }
+    }
+
+    #[test]
+    fn test_prune() {
+        fn best_score(board: &Board<5, 3, 3>) -> i32 {
+            board
+                .compute_all_regions()
+                .iter()
+                .map(|region| {
+                    let mut copy = board.clone();
+                    copy.play_region(region);
+                    best_score(&copy)
+                })
+                .max()
+                .unwrap_or(board.get_score())
+        }
+
+        let board: Board<5, 3, 3> = Board::new([[0, 1, 1, 2, 0], [2, 0, 1, 0, 2], [1, 2, 0, 0, 1]]);
+        let mut tabucolor = TabuColor::default();
+        tabucolor.settings.prune = true;
+
+        // the tree is small enough for branch and bound to cut it all
+        let budget = Budget::default().with_iterations(Some(1000000));
+        let (probe, iterations) = tabucolor.solve_sequential(&board, &budget);
+        assert!(iterations < 1000000);
+        assert_eq!(probe.get_score(), best_score(&board));
+    }
 
-//     #[test]
-//     fn test_probs() {}
-// }
+    /// Iterations per second for an increasing number of threads, run with
+    /// `cargo test --release -- --ignored --nocapture bench_threads`.
+    ///
+    ///
+    /// Only a single core machine has been measured so far, where the extra
+    /// threads can only add contention: no speedup is claimed until the bench
+    /// has been run on several cores. There, 1 thread ran 392k iterations/s,
+    /// and the atomic statistics took 2, 4 and 8 threads from 156k, 148k and
+    /// 218k iterations/s with a lock per node to 227k, 366k and 341k.
+    #[test]
+    #[ignore]
+    fn bench_threads() {
+        let board = crate::test_util::mixed_board();
+        for threads in [1, 2, 4, 8] {
+            let tabucolor = TabuColor {
+                threads,
+                ..TabuColor::default()
+            };
+            let start = Instant::now();
+            let (_, iterations) = if threads > 1 {
+                tabucolor.solve_parallel(&board, &Budget::from_secs(5.0))
+            } else {
+                tabucolor.solve_sequential(&board, &Budget::from_secs(5.0))
+            };
+            println!(
+                "threads: {}, iterations/s: {:.0}",
+                threads,
+                iterations as f64 / start.elapsed().as_secs_f64()
+            );
+        }
+    }
+}
@@ -0,0 +1,150 @@
+use std::fmt::{self, Display, Formatter};
+
+use crate::board::{Board, MoveError};
+
+/// First problem found while replaying an action string; `step` counts the
+/// moves from 0.
+#[derive(Debug, PartialEq)]
+pub enum VerifyError {
+    InvalidAction { step: usize, action: String },
+    OutOfBounds { step: usize, x: usize, y: usize },
+    EmptyCell { step: usize, x: usize, y: usize },
+    SingletonRegion { step: usize, x: usize, y: usize },
+    GameOver { step: usize },
+}
+
+impl Display for VerifyError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        match self {
+            VerifyError::InvalidAction { step, action } => {
+                write!(f, "move {}: cannot parse {:?} as \"x y\"", step, action)
+            }
+            VerifyError::OutOfBounds { step, x, y } => {
+                write!(f, "move {}: ({}, {}) is out of the board", step, x, y)
+            }
+            VerifyError::EmptyCell { step, x, y } => {
+                write!(f, "move {}: cell ({}, {}) is empty", step, x, y)
+            }
+            VerifyError::SingletonRegion { step, x, y } => {
+                write!(
+                    f,
+                    "move {}: cell ({}, {}) has no neighbor of its color",
+                    step, x, y
+                )
+            }
+            VerifyError::GameOver { step } => {
+                write!(f, "move {}: the game is already over", step)
+            }
+        }
+    }
+}
+
+/// Plays the `"x y;x y;..."` format printed by `Board::get_actions_str` on
+/// `board`, stopping at the first move that is not legal.
+pub fn replay<const W: usize, const H: usize, const C: usize>(
+    board: &Board<W, H, C>,
+    actions: &str,
+) -> Result<Board<W, H, C>, VerifyError> {
+    let mut board = board.clone();
+
+    let actions = actions
+        .split(';')
+        .filter(|action| !action.trim().is_empty());
+    for (step, action) in actions.enumerate() {
+        let coordinates: Vec<usize> = action
+            .split_whitespace()
+            .map(|c| c.parse())
+            .collect::<Result<_, _>>()
+            .map_err(|_| VerifyError::InvalidAction {
+                step,
+                action: action.to_string(),
+            })?;
+        let (x, y) = match coordinates[..] {
+            [x, y] => (x, y),
+            _ => {
+                return Err(VerifyError::InvalidAction {
+                    step,
+                    action: action.to_string(),
+                })
+            }
+        };
+
+        board.try_play(x, y).map_err(|error| match error {
+            MoveError::OutOfBounds => VerifyError::OutOfBounds { step, x, y },
+            MoveError::EmptyCell => VerifyError::EmptyCell { step, x, y },
+            MoveError::SingletonRegion => VerifyError::SingletonRegion { step, x, y },
+            MoveError::GameOver => VerifyError::GameOver { step },
+            MoveError::StaleRegion => unreachable!("try_play plays the group of the cell"),
+        })?;
+    }
+
+    Ok(board)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_util::blocks_board;
+
+    #[test]
+    fn test_replay() {
+        let board = blocks_board();
+        let mut played = board.clone();
+        played.play(0, 0);
+        played.play(4, 2);
+
+        let replayed = replay(&board, &played.get_actions_str()).unwrap();
+        assert_eq!(replayed.get_score(), played.get_score());
+        assert_eq!(replayed, played);
+
+        assert_eq!(replay(&board, "").unwrap(), board);
+    }
+
+    #[test]
+    fn test_replay_errors() {
+        let board = blocks_board();
+        // two cells of color 1 and a lone cell of color 2 on a board of 0
+        let mut cells = [[0i8; 15]; 15];
+        cells[0][1] = 1;
+        cells[0][2] = 1;
+        cells[0][4] = 2;
+        let cells: Board = Board::new(cells);
+
+        assert_eq!(
+            replay(&board, "0 0;15 3"),
+            Err(VerifyError::OutOfBounds {
+                step: 1,
+                x: 15,
+                y: 3
+            })
+        );
+        assert_eq!(
+            replay(&board, "0 0;a 3"),
+            Err(VerifyError::InvalidAction {
+                step: 1,
+                action: "a 3".to_string()
+            })
+        );
+        assert_eq!(
+            replay(&cells, "4 0"),
+            Err(VerifyError::SingletonRegion {
+                step: 0,
+                x: 4,
+                y: 0
+            })
+        );
+        // the 0s are removed: the other cells fall in the first three columns
+        assert_eq!(
+            replay(&cells, "0 0;4 0"),
+            Err(VerifyError::EmptyCell {
+                step: 1,
+                x: 4,
+                y: 0
+            })
+        );
+        assert_eq!(
+            replay(&cells, "0 0;0 0;0 0"),
+            Err(VerifyError::GameOver { step: 2 })
+        );
+    }
+}